@@ -40,12 +40,12 @@ pub fn get_action_list_from_current_config(reaper: &Reaper) -> ReaperActionList
             // 4) Try to load it, or fall back to an empty list on any I/O error
             match ReaperActionList::load_from_file(keymap_file.as_std_path()) {
                 Ok(list) => {
-                    println!("✔️ Loaded {} entries from {:?}", list.0.len(), keymap_file);
+                    println!("✔️ Loaded {} entries from {:?}", list.entries.len(), keymap_file);
                     list
                 }
                 Err(e) => {
                     eprintln!("⚠️ Failed to load keymap from {:?}: {}", keymap_file, e);
-                    ReaperActionList(Vec::new())
+                    ReaperActionList::default()
                 }
             }
         })
@@ -0,0 +1,442 @@
+//! Portable keymap bundles: a zip holding a keymap together with the scripts its SCR
+//! entries run, so it can be shared with another REAPER installation.
+//!
+//! Layout of a bundle:
+//! - `keymap.reaperkeymap`: the keymap, with bundled SCR paths relative to `Scripts/`
+//! - `manifest.json`: the [`BundleManifest`]
+//! - `Scripts/...`: the scripts, under their paths relative to REAPER's `Scripts` folder
+
+use crate::action_list::{
+    ConflictChoice, LocatedParseError, ReaperActionList, ReaperEntry, ScriptEntry,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+const KEYMAP_NAME: &str = "keymap.reaperkeymap";
+const MANIFEST_NAME: &str = "manifest.json";
+const SCRIPTS_DIR: &str = "Scripts";
+
+/// What a bundle contains.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub scripts: Vec<BundledScript>,
+    pub warnings: Vec<BundleWarning>,
+}
+
+/// One script stored in a bundle.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundledScript {
+    pub command_id: String,
+    /// The SCR path in the exported keymap.
+    pub original_path: String,
+    /// Path relative to `Scripts/`, inside the bundle and after import.
+    pub bundle_path: String,
+}
+
+/// Problems that didn't stop an export.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BundleWarning {
+    /// The script an SCR entry points at doesn't exist; its entry is kept as-is.
+    MissingScript { command_id: String, path: String },
+}
+
+/// How [`ReaperActionList::import_bundle`] treats what already exists.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BundleImportPolicy {
+    /// Folder under `Scripts/` to extract into; empty keeps the exported layout.
+    pub script_dir: String,
+    /// Overwrite script files that already exist instead of keeping them.
+    pub overwrite_scripts: bool,
+    /// Let the bundle's entries replace existing definitions and bindings with the same
+    /// command ID or input, instead of keeping the existing ones.
+    pub replace_existing: bool,
+}
+
+/// Errors while writing or reading a bundle.
+#[derive(Debug)]
+pub enum BundleError {
+    Io(io::Error),
+    Zip(ZipError),
+    Manifest(serde_json::Error),
+    /// The bundle lacks one of its required files.
+    MissingFile(&'static str),
+    InvalidKeymap(Vec<LocatedParseError>),
+}
+
+impl From<io::Error> for BundleError {
+    fn from(e: io::Error) -> Self {
+        BundleError::Io(e)
+    }
+}
+
+impl From<ZipError> for BundleError {
+    fn from(e: ZipError) -> Self {
+        BundleError::Zip(e)
+    }
+}
+
+impl From<serde_json::Error> for BundleError {
+    fn from(e: serde_json::Error) -> Self {
+        BundleError::Manifest(e)
+    }
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::Io(e) => write!(f, "I/O error: {}", e),
+            BundleError::Zip(e) => write!(f, "zip error: {}", e),
+            BundleError::Manifest(e) => write!(f, "invalid bundle manifest: {}", e),
+            BundleError::MissingFile(name) => write!(f, "bundle has no {}", name),
+            BundleError::InvalidKeymap(errors) => {
+                write!(f, "bundle keymap has {} invalid lines", errors.len())?;
+                if let Some(first) = errors.first() {
+                    write!(f, " (first at {})", first)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+/// Where an SCR path points: relative paths are resolved against `Scripts/`.
+fn resolve_script(resource_root: &Path, path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        resource_root.join(SCRIPTS_DIR).join(path)
+    }
+}
+
+/// The bundle path of a script: its SCR path when that is a plain relative path, a
+/// per-command folder for anything else.
+fn bundle_path_for(script: &ScriptEntry) -> String {
+    let normalized = script.path.replace('\\', "/");
+    let plain = Path::new(&normalized)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)));
+    if plain && !normalized.contains(':') {
+        normalized
+    } else {
+        let file_name = normalized.rsplit('/').next().unwrap_or(&normalized);
+        format!("external/{}/{}", script.command_id, file_name)
+    }
+}
+
+/// `dir` and `path` joined with `/`, for SCR paths.
+fn join_script_path(dir: &str, path: &str) -> String {
+    let dir = dir.trim_matches(['/', '\\']);
+    if dir.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}/{}", dir.replace('\\', "/"), path)
+    }
+}
+
+impl ReaperActionList {
+    /// Write a bundle to `out` with this keymap and every script its SCR entries run,
+    /// resolving relative script paths against `resource_root`'s `Scripts` folder.
+    /// Scripts that can't be found are reported in the manifest's warnings.
+    pub fn export_bundle(
+        &self,
+        resource_root: &Path,
+        out: &Path,
+    ) -> Result<BundleManifest, BundleError> {
+        let mut zip = ZipWriter::new(fs::File::create(out)?);
+        let options = SimpleFileOptions::default();
+        let mut manifest = BundleManifest::default();
+        let mut keymap = self.clone();
+        let mut written = BTreeSet::new();
+
+        for entry in &mut keymap.entries {
+            let ReaperEntry::Script(script) = entry else {
+                continue;
+            };
+            let source = resolve_script(resource_root, &script.path);
+            if !source.is_file() {
+                manifest.warnings.push(BundleWarning::MissingScript {
+                    command_id: script.command_id.clone(),
+                    path: script.path.clone(),
+                });
+                continue;
+            }
+            let bundle_path = bundle_path_for(script);
+            if written.insert(bundle_path.clone()) {
+                zip.start_file(format!("{}/{}", SCRIPTS_DIR, bundle_path), options)?;
+                zip.write_all(&fs::read(&source)?)?;
+            }
+            manifest.scripts.push(BundledScript {
+                command_id: script.command_id.clone(),
+                original_path: script.path.clone(),
+                bundle_path: bundle_path.clone(),
+            });
+            script.path = bundle_path;
+        }
+
+        zip.start_file(KEYMAP_NAME, options)?;
+        write!(zip, "{}", keymap)?;
+        zip.start_file(MANIFEST_NAME, options)?;
+        zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+        zip.finish()?;
+        Ok(manifest)
+    }
+
+    /// Extract a bundle's scripts under `resource_root`'s `Scripts` folder, point its
+    /// SCR entries at them, and merge its keymap into this list according to `policy`.
+    /// Returns the bundle's manifest.
+    pub fn import_bundle(
+        &mut self,
+        path: &Path,
+        resource_root: &Path,
+        policy: &BundleImportPolicy,
+    ) -> Result<BundleManifest, BundleError> {
+        let mut archive = ZipArchive::new(fs::File::open(path)?)?;
+        let manifest: BundleManifest = {
+            let file = match archive.by_name(MANIFEST_NAME) {
+                Err(ZipError::FileNotFound) => return Err(BundleError::MissingFile(MANIFEST_NAME)),
+                other => other?,
+            };
+            serde_json::from_reader(file)?
+        };
+        let mut keymap_text = String::new();
+        match archive.by_name(KEYMAP_NAME) {
+            Err(ZipError::FileNotFound) => return Err(BundleError::MissingFile(KEYMAP_NAME)),
+            other => other?.read_to_string(&mut keymap_text)?,
+        };
+        let mut incoming =
+            ReaperActionList::from_lines(keymap_text.lines()).map_err(BundleError::InvalidKeymap)?;
+
+        let scripts_root = resource_root
+            .join(SCRIPTS_DIR)
+            .join(policy.script_dir.trim_matches(['/', '\\']));
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index)?;
+            // Refuses absolute paths and `..`, so nothing lands outside `Scripts/`
+            let Some(name) = file.enclosed_name() else {
+                continue;
+            };
+            let Ok(relative) = name.strip_prefix(SCRIPTS_DIR) else {
+                continue;
+            };
+            if file.is_dir() {
+                continue;
+            }
+            let target = scripts_root.join(relative);
+            if target.exists() && !policy.overwrite_scripts {
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            io::copy(&mut file, &mut fs::File::create(&target)?)?;
+        }
+
+        for entry in &mut incoming.entries {
+            if let ReaperEntry::Script(script) = entry
+                && manifest.scripts.iter().any(|s| s.bundle_path == script.path)
+            {
+                script.path = join_script_path(&policy.script_dir, &script.path);
+            }
+        }
+
+        let mut bindings = Vec::new();
+        for entry in incoming.entries {
+            let ReaperEntry::Key(key) = entry else {
+                let existing = self.entries.iter().position(|e| {
+                    e.kind() == entry.kind() && definition_id(e) == definition_id(&entry)
+                });
+                match existing {
+                    Some(index) if policy.replace_existing => self.entries[index] = entry,
+                    Some(_) => {}
+                    None => {
+                        self.insert_sorted_within_section(entry);
+                    }
+                }
+                continue;
+            };
+            bindings.push(key);
+        }
+        let choice = if policy.replace_existing {
+            ConflictChoice::ReplaceWithNew
+        } else {
+            ConflictChoice::KeepExisting
+        };
+        self.import_bindings(bindings, |_, _| choice.clone());
+
+        Ok(manifest)
+    }
+}
+
+/// The command ID an SCR or ACT entry defines.
+fn definition_id(entry: &ReaperEntry) -> Option<&str> {
+    match entry {
+        ReaperEntry::Script(s) => Some(&s.command_id),
+        ReaperEntry::Action(a) => Some(&a.command_id),
+        ReaperEntry::Key(_) | ReaperEntry::Raw(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_script(resource_root: &Path, relative: &str, content: &str) {
+        let path = resource_root.join(SCRIPTS_DIR).join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn sample_list() -> ReaperActionList {
+        ReaperActionList::from_lines([
+            r#"SCR 4 0 RS111 "Custom: tool.lua" "My Tools/tool.lua""#,
+            r#"SCR 4 32060 RS222 "Custom: midi thing.lua" "My Tools/midi thing.lua""#,
+            r#"SCR 4 0 RS333 "Custom: gone.lua" gone.lua"#,
+            r#"ACT 3 0 "RA444" "Custom: both" _RS111 _RS333"#,
+            "KEY 9 84 _RS111 0 # Main : Cmd+T : Script: tool.lua",
+            "KEY 1 77 _RS222 32060 # MIDI Editor : M : Script: midi thing.lua",
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let source_root = tempfile::tempdir().unwrap();
+        write_script(source_root.path(), "My Tools/tool.lua", "-- tool");
+        write_script(source_root.path(), "My Tools/midi thing.lua", "-- midi");
+        let out = source_root.path().join("shared.zip");
+
+        let list = sample_list();
+        let manifest = list.export_bundle(source_root.path(), &out).unwrap();
+        assert_eq!(manifest.scripts.len(), 2);
+        assert_eq!(manifest.scripts[1].bundle_path, "My Tools/midi thing.lua");
+        assert_eq!(
+            manifest.warnings,
+            vec![BundleWarning::MissingScript {
+                command_id: "RS333".to_string(),
+                path: "gone.lua".to_string(),
+            }]
+        );
+
+        // Import as-is into an empty installation
+        let target_root = tempfile::tempdir().unwrap();
+        let mut imported = ReaperActionList::default();
+        let read = imported
+            .import_bundle(&out, target_root.path(), &BundleImportPolicy::default())
+            .unwrap();
+        assert_eq!(read, manifest);
+        assert_eq!(
+            fs::read_to_string(target_root.path().join("Scripts/My Tools/tool.lua")).unwrap(),
+            "-- tool"
+        );
+        assert_eq!(imported.counts(), list.counts());
+        assert!(imported.find_script("RS222").unwrap().equivalent_to(list.find_script("RS222").unwrap()));
+
+        // Import into a subfolder: SCR paths follow the scripts
+        let policy = BundleImportPolicy {
+            script_dir: "Imported".to_string(),
+            ..BundleImportPolicy::default()
+        };
+        let mut imported = ReaperActionList::default();
+        imported.import_bundle(&out, target_root.path(), &policy).unwrap();
+        assert_eq!(imported.find_script("RS111").unwrap().path, "Imported/My Tools/tool.lua");
+        assert_eq!(imported.find_script("RS333").unwrap().path, "gone.lua");
+        assert!(target_root.path().join("Scripts/Imported/My Tools/midi thing.lua").is_file());
+    }
+
+    #[test]
+    fn test_bundle_import_keeps_existing_unless_replacing() {
+        let source_root = tempfile::tempdir().unwrap();
+        write_script(source_root.path(), "My Tools/tool.lua", "-- new tool");
+        write_script(source_root.path(), "My Tools/midi thing.lua", "-- midi");
+        let out = source_root.path().join("shared.zip");
+        sample_list().export_bundle(source_root.path(), &out).unwrap();
+
+        let target_root = tempfile::tempdir().unwrap();
+        write_script(target_root.path(), "My Tools/tool.lua", "-- old tool");
+        let existing = ReaperActionList::from_lines(["KEY 9 84 40001 0 # Main : Cmd+T : Undo"]).unwrap();
+
+        let mut kept = existing.clone();
+        kept.import_bundle(&out, target_root.path(), &BundleImportPolicy::default())
+            .unwrap();
+        assert_eq!(kept.keys()[0].command_id, "40001");
+        assert_eq!(
+            fs::read_to_string(target_root.path().join("Scripts/My Tools/tool.lua")).unwrap(),
+            "-- old tool"
+        );
+
+        let mut replaced = existing;
+        let policy = BundleImportPolicy {
+            overwrite_scripts: true,
+            replace_existing: true,
+            ..BundleImportPolicy::default()
+        };
+        replaced.import_bundle(&out, target_root.path(), &policy).unwrap();
+        assert_eq!(replaced.keys()[0].command_id, "_RS111");
+        assert_eq!(
+            fs::read_to_string(target_root.path().join("Scripts/My Tools/tool.lua")).unwrap(),
+            "-- new tool"
+        );
+    }
+
+    #[test]
+    fn test_bundle_keeps_header_and_section_order() {
+        let source_root = tempfile::tempdir().unwrap();
+        write_script(source_root.path(), "My Tools/tool.lua", "-- tool");
+        let out = source_root.path().join("shared.zip");
+        let mut list = sample_list();
+        list.set_header(["# Shared tools", ""]);
+        list.export_bundle(source_root.path(), &out).unwrap();
+
+        let mut keymap_text = String::new();
+        ZipArchive::new(fs::File::open(&out).unwrap())
+            .unwrap()
+            .by_name(KEYMAP_NAME)
+            .unwrap()
+            .read_to_string(&mut keymap_text)
+            .unwrap();
+        assert!(keymap_text.starts_with("# Shared tools\n\nSCR 4 0 RS111 "));
+
+        // New SCR and ACT entries go next to their section, not to the end of the file
+        let target_root = tempfile::tempdir().unwrap();
+        let mut existing = ReaperActionList::from_lines([
+            "KEY 1 65 40001 0 # Main : A : Undo",
+            "KEY 1 66 40002 32060 # MIDI Editor : B : Redo",
+        ])
+        .unwrap();
+        existing
+            .import_bundle(&out, target_root.path(), &BundleImportPolicy::default())
+            .unwrap();
+        let sections: Vec<u32> = existing
+            .iter()
+            .filter_map(|e| e.section().map(|s| s.as_u32()))
+            .collect();
+        assert_eq!(sections, [0, 0, 0, 0, 0, 32060, 32060, 32060]);
+        assert_eq!(existing.entries[0].as_key().unwrap().command_id, "40001");
+        assert_eq!(existing.entries[5].as_key().unwrap().command_id, "40002");
+    }
+
+    #[test]
+    fn test_bundle_paths_for_absolute_scripts() {
+        let script = |path: &str| ScriptEntry {
+            termination_behavior: crate::action_list::TerminationBehavior::Prompt,
+            section: crate::sections::ReaperActionSection::Main,
+            command_id: "RS1".to_string(),
+            description: "Custom: x.lua".to_string(),
+            path: path.to_string(),
+        };
+        assert_eq!(bundle_path_for(&script(r"Tools\x.lua")), "Tools/x.lua");
+        assert_eq!(bundle_path_for(&script(r"C:\Scripts\x.lua")), "external/RS1/x.lua");
+        assert_eq!(bundle_path_for(&script("/home/me/x.lua")), "external/RS1/x.lua");
+        assert_eq!(bundle_path_for(&script("../x.lua")), "external/RS1/x.lua");
+    }
+}
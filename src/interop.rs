@@ -0,0 +1,591 @@
+//! Conversions between this crate's [`KeyCode`]/[`Modifiers`] and the key types of other
+//! crates, behind the `keyboard-types` and `egui` features.
+//!
+//! Every key mapping lives in a single table per crate and is used in both directions, so a
+//! key converts back to exactly what it came from. Keys without a counterpart on the other
+//! side are errors rather than best guesses.
+
+use crate::keycodes::KeyCode;
+use crate::modifiers::Modifiers;
+use std::fmt;
+
+/// A key or modifier set that has no counterpart on the other side of a conversion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    /// This crate's key has no counterpart.
+    UnmappedKey(KeyCode),
+    /// This crate's modifiers have no counterpart (e.g. `SPECIAL_INPUT`).
+    UnmappedModifiers(Modifiers),
+    /// The other crate's key has no [`KeyCode`], by its `Debug` name.
+    UnmappedForeignKey(String),
+    /// The other crate's modifiers include one this crate can't express, by its `Debug` name.
+    UnmappedForeignModifiers(String),
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionError::UnmappedKey(key) => write!(f, "Key {:?} has no counterpart", key),
+            ConversionError::UnmappedModifiers(modifiers) => {
+                write!(f, "Modifiers {:?} have no counterpart", modifiers)
+            }
+            ConversionError::UnmappedForeignKey(key) => write!(f, "Key {} has no KeyCode", key),
+            ConversionError::UnmappedForeignModifiers(modifiers) => {
+                write!(f, "Modifiers {} can't be expressed as Modifiers", modifiers)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// Look `key` up in the left column of `table`.
+fn lookup_right<T: Copy>(table: &[(KeyCode, T)], key: KeyCode) -> Option<T> {
+    table.iter().find(|(k, _)| *k == key).map(|(_, t)| *t)
+}
+
+/// Look `key` up in the right column of `table`.
+fn lookup_left<T: PartialEq>(table: &[(KeyCode, T)], key: &T) -> Option<KeyCode> {
+    table.iter().find(|(_, t)| t == key).map(|(k, _)| *k)
+}
+
+#[cfg(feature = "keyboard-types")]
+mod keyboard_types_impl {
+    use super::*;
+    use keyboard_types::Code;
+
+    /// `KeyCode` ↔ physical `Code`, assuming a US layout for the OEM keys.
+    pub(super) const CODES: &[(KeyCode, Code)] = &[
+        (KeyCode::Backspace, Code::Backspace),
+        (KeyCode::Tab, Code::Tab),
+        (KeyCode::Enter, Code::Enter),
+        (KeyCode::Pause, Code::Pause),
+        (KeyCode::CapsLock, Code::CapsLock),
+        (KeyCode::Kana, Code::KanaMode),
+        (KeyCode::Escape, Code::Escape),
+        (KeyCode::Convert, Code::Convert),
+        (KeyCode::NonConvert, Code::NonConvert),
+        (KeyCode::Space, Code::Space),
+        (KeyCode::PageUp, Code::PageUp),
+        (KeyCode::PageDown, Code::PageDown),
+        (KeyCode::End, Code::End),
+        (KeyCode::Home, Code::Home),
+        (KeyCode::Left, Code::ArrowLeft),
+        (KeyCode::Up, Code::ArrowUp),
+        (KeyCode::Right, Code::ArrowRight),
+        (KeyCode::Down, Code::ArrowDown),
+        (KeyCode::Select, Code::Select),
+        (KeyCode::Snapshot, Code::PrintScreen),
+        (KeyCode::Insert, Code::Insert),
+        (KeyCode::Delete, Code::Delete),
+        (KeyCode::Help, Code::Help),
+        (KeyCode::Key0, Code::Digit0),
+        (KeyCode::Key1, Code::Digit1),
+        (KeyCode::Key2, Code::Digit2),
+        (KeyCode::Key3, Code::Digit3),
+        (KeyCode::Key4, Code::Digit4),
+        (KeyCode::Key5, Code::Digit5),
+        (KeyCode::Key6, Code::Digit6),
+        (KeyCode::Key7, Code::Digit7),
+        (KeyCode::Key8, Code::Digit8),
+        (KeyCode::Key9, Code::Digit9),
+        (KeyCode::A, Code::KeyA),
+        (KeyCode::B, Code::KeyB),
+        (KeyCode::C, Code::KeyC),
+        (KeyCode::D, Code::KeyD),
+        (KeyCode::E, Code::KeyE),
+        (KeyCode::F, Code::KeyF),
+        (KeyCode::G, Code::KeyG),
+        (KeyCode::H, Code::KeyH),
+        (KeyCode::I, Code::KeyI),
+        (KeyCode::J, Code::KeyJ),
+        (KeyCode::K, Code::KeyK),
+        (KeyCode::L, Code::KeyL),
+        (KeyCode::M, Code::KeyM),
+        (KeyCode::N, Code::KeyN),
+        (KeyCode::O, Code::KeyO),
+        (KeyCode::P, Code::KeyP),
+        (KeyCode::Q, Code::KeyQ),
+        (KeyCode::R, Code::KeyR),
+        (KeyCode::S, Code::KeyS),
+        (KeyCode::T, Code::KeyT),
+        (KeyCode::U, Code::KeyU),
+        (KeyCode::V, Code::KeyV),
+        (KeyCode::W, Code::KeyW),
+        (KeyCode::X, Code::KeyX),
+        (KeyCode::Y, Code::KeyY),
+        (KeyCode::Z, Code::KeyZ),
+        (KeyCode::LSuper, Code::MetaLeft),
+        (KeyCode::RSuper, Code::MetaRight),
+        (KeyCode::Apps, Code::ContextMenu),
+        (KeyCode::Sleep, Code::Sleep),
+        (KeyCode::Numpad0, Code::Numpad0),
+        (KeyCode::Numpad1, Code::Numpad1),
+        (KeyCode::Numpad2, Code::Numpad2),
+        (KeyCode::Numpad3, Code::Numpad3),
+        (KeyCode::Numpad4, Code::Numpad4),
+        (KeyCode::Numpad5, Code::Numpad5),
+        (KeyCode::Numpad6, Code::Numpad6),
+        (KeyCode::Numpad7, Code::Numpad7),
+        (KeyCode::Numpad8, Code::Numpad8),
+        (KeyCode::Numpad9, Code::Numpad9),
+        (KeyCode::Multiply, Code::NumpadMultiply),
+        (KeyCode::Add, Code::NumpadAdd),
+        (KeyCode::Subtract, Code::NumpadSubtract),
+        (KeyCode::Decimal, Code::NumpadDecimal),
+        (KeyCode::Divide, Code::NumpadDivide),
+        (KeyCode::NumpadEnter, Code::NumpadEnter),
+        (KeyCode::F1, Code::F1),
+        (KeyCode::F2, Code::F2),
+        (KeyCode::F3, Code::F3),
+        (KeyCode::F4, Code::F4),
+        (KeyCode::F5, Code::F5),
+        (KeyCode::F6, Code::F6),
+        (KeyCode::F7, Code::F7),
+        (KeyCode::F8, Code::F8),
+        (KeyCode::F9, Code::F9),
+        (KeyCode::F10, Code::F10),
+        (KeyCode::F11, Code::F11),
+        (KeyCode::F12, Code::F12),
+        (KeyCode::F13, Code::F13),
+        (KeyCode::F14, Code::F14),
+        (KeyCode::F15, Code::F15),
+        (KeyCode::F16, Code::F16),
+        (KeyCode::F17, Code::F17),
+        (KeyCode::F18, Code::F18),
+        (KeyCode::F19, Code::F19),
+        (KeyCode::F20, Code::F20),
+        (KeyCode::F21, Code::F21),
+        (KeyCode::F22, Code::F22),
+        (KeyCode::F23, Code::F23),
+        (KeyCode::F24, Code::F24),
+        (KeyCode::NumLock, Code::NumLock),
+        (KeyCode::ScrollLock, Code::ScrollLock),
+        (KeyCode::LShift, Code::ShiftLeft),
+        (KeyCode::RShift, Code::ShiftRight),
+        (KeyCode::LControl, Code::ControlLeft),
+        (KeyCode::RControl, Code::ControlRight),
+        (KeyCode::LAlt, Code::AltLeft),
+        (KeyCode::RAlt, Code::AltRight),
+        (KeyCode::BrowserBack, Code::BrowserBack),
+        (KeyCode::BrowserForward, Code::BrowserForward),
+        (KeyCode::BrowserRefresh, Code::BrowserRefresh),
+        (KeyCode::BrowserStop, Code::BrowserStop),
+        (KeyCode::BrowserSearch, Code::BrowserSearch),
+        (KeyCode::BrowserFavorites, Code::BrowserFavorites),
+        (KeyCode::BrowserHome, Code::BrowserHome),
+        (KeyCode::VolumeMute, Code::AudioVolumeMute),
+        (KeyCode::VolumeDown, Code::AudioVolumeDown),
+        (KeyCode::VolumeUp, Code::AudioVolumeUp),
+        (KeyCode::MediaNextTrack, Code::MediaTrackNext),
+        (KeyCode::MediaPrevTrack, Code::MediaTrackPrevious),
+        (KeyCode::MediaStop, Code::MediaStop),
+        (KeyCode::MediaPlayPause, Code::MediaPlayPause),
+        (KeyCode::LaunchMail, Code::LaunchMail),
+        (KeyCode::LaunchMediaSelect, Code::MediaSelect),
+        (KeyCode::LaunchApp1, Code::LaunchApp1),
+        (KeyCode::LaunchApp2, Code::LaunchApp2),
+        (KeyCode::OEM1, Code::Semicolon),
+        (KeyCode::OEMPlus, Code::Equal),
+        (KeyCode::OEMComma, Code::Comma),
+        (KeyCode::OEMMinus, Code::Minus),
+        (KeyCode::OEMPeriod, Code::Period),
+        (KeyCode::OEM2, Code::Slash),
+        (KeyCode::OEM3, Code::Backquote),
+        (KeyCode::OEM4, Code::BracketLeft),
+        (KeyCode::OEM5, Code::Backslash),
+        (KeyCode::OEM6, Code::BracketRight),
+        (KeyCode::OEM7, Code::Quote),
+        (KeyCode::OEM102, Code::IntlBackslash),
+    ];
+
+    /// Modifiers pairs; `SUPER` is `META` (Cmd on macOS, the Windows key elsewhere).
+    const MODIFIERS: &[(Modifiers, keyboard_types::Modifiers)] = &[
+        (Modifiers::SHIFT, keyboard_types::Modifiers::SHIFT),
+        (Modifiers::CONTROL, keyboard_types::Modifiers::CONTROL),
+        (Modifiers::ALT, keyboard_types::Modifiers::ALT),
+        (Modifiers::SUPER, keyboard_types::Modifiers::META),
+    ];
+
+    /// Lock states aren't part of a shortcut, so they are dropped rather than rejected.
+    const IGNORED: keyboard_types::Modifiers = keyboard_types::Modifiers::CAPS_LOCK
+        .union(keyboard_types::Modifiers::NUM_LOCK)
+        .union(keyboard_types::Modifiers::SCROLL_LOCK)
+        .union(keyboard_types::Modifiers::FN_LOCK)
+        .union(keyboard_types::Modifiers::SYMBOL_LOCK);
+
+    impl TryFrom<KeyCode> for Code {
+        type Error = ConversionError;
+
+        fn try_from(key: KeyCode) -> Result<Self, Self::Error> {
+            lookup_right(CODES, key).ok_or(ConversionError::UnmappedKey(key))
+        }
+    }
+
+    impl TryFrom<Code> for KeyCode {
+        type Error = ConversionError;
+
+        fn try_from(code: Code) -> Result<Self, Self::Error> {
+            lookup_left(CODES, &code)
+                .ok_or_else(|| ConversionError::UnmappedForeignKey(format!("{:?}", code)))
+        }
+    }
+
+    impl TryFrom<Modifiers> for keyboard_types::Modifiers {
+        type Error = ConversionError;
+
+        fn try_from(modifiers: Modifiers) -> Result<Self, Self::Error> {
+            if modifiers.is_special_input() {
+                return Err(ConversionError::UnmappedModifiers(modifiers));
+            }
+            Ok(MODIFIERS
+                .iter()
+                .filter(|(ours, _)| modifiers.contains(*ours))
+                .fold(keyboard_types::Modifiers::empty(), |acc, (_, theirs)| {
+                    acc | *theirs
+                }))
+        }
+    }
+
+    impl TryFrom<keyboard_types::Modifiers> for Modifiers {
+        type Error = ConversionError;
+
+        fn try_from(modifiers: keyboard_types::Modifiers) -> Result<Self, Self::Error> {
+            let mut remaining = modifiers - IGNORED;
+            let mut result = Modifiers::empty();
+            for (ours, theirs) in MODIFIERS {
+                if remaining.contains(*theirs) {
+                    result |= *ours;
+                    remaining -= *theirs;
+                }
+            }
+            if remaining.is_empty() {
+                Ok(result)
+            } else {
+                Err(ConversionError::UnmappedForeignModifiers(format!("{:?}", remaining)))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "egui")]
+mod egui_impl {
+    use super::*;
+    use crate::action_list::ReaperActionInput;
+    use crate::modifiers::Platform;
+    use egui::Key;
+
+    /// `KeyCode` ↔ logical egui `Key`, assuming a US layout for the OEM keys. egui has no
+    /// numpad or left/right modifier keys.
+    pub(super) const KEYS: &[(KeyCode, Key)] = &[
+        (KeyCode::Down, Key::ArrowDown),
+        (KeyCode::Left, Key::ArrowLeft),
+        (KeyCode::Right, Key::ArrowRight),
+        (KeyCode::Up, Key::ArrowUp),
+        (KeyCode::Escape, Key::Escape),
+        (KeyCode::Tab, Key::Tab),
+        (KeyCode::Backspace, Key::Backspace),
+        (KeyCode::Enter, Key::Enter),
+        (KeyCode::Space, Key::Space),
+        (KeyCode::Insert, Key::Insert),
+        (KeyCode::Delete, Key::Delete),
+        (KeyCode::Home, Key::Home),
+        (KeyCode::End, Key::End),
+        (KeyCode::PageUp, Key::PageUp),
+        (KeyCode::PageDown, Key::PageDown),
+        (KeyCode::OEMComma, Key::Comma),
+        (KeyCode::OEM5, Key::Backslash),
+        (KeyCode::OEM2, Key::Slash),
+        (KeyCode::OEM4, Key::OpenBracket),
+        (KeyCode::OEM6, Key::CloseBracket),
+        (KeyCode::OEM3, Key::Backtick),
+        (KeyCode::OEMMinus, Key::Minus),
+        (KeyCode::OEMPeriod, Key::Period),
+        (KeyCode::OEMPlus, Key::Equals),
+        (KeyCode::OEM1, Key::Semicolon),
+        (KeyCode::OEM7, Key::Quote),
+        (KeyCode::Key0, Key::Num0),
+        (KeyCode::Key1, Key::Num1),
+        (KeyCode::Key2, Key::Num2),
+        (KeyCode::Key3, Key::Num3),
+        (KeyCode::Key4, Key::Num4),
+        (KeyCode::Key5, Key::Num5),
+        (KeyCode::Key6, Key::Num6),
+        (KeyCode::Key7, Key::Num7),
+        (KeyCode::Key8, Key::Num8),
+        (KeyCode::Key9, Key::Num9),
+        (KeyCode::A, Key::A),
+        (KeyCode::B, Key::B),
+        (KeyCode::C, Key::C),
+        (KeyCode::D, Key::D),
+        (KeyCode::E, Key::E),
+        (KeyCode::F, Key::F),
+        (KeyCode::G, Key::G),
+        (KeyCode::H, Key::H),
+        (KeyCode::I, Key::I),
+        (KeyCode::J, Key::J),
+        (KeyCode::K, Key::K),
+        (KeyCode::L, Key::L),
+        (KeyCode::M, Key::M),
+        (KeyCode::N, Key::N),
+        (KeyCode::O, Key::O),
+        (KeyCode::P, Key::P),
+        (KeyCode::Q, Key::Q),
+        (KeyCode::R, Key::R),
+        (KeyCode::S, Key::S),
+        (KeyCode::T, Key::T),
+        (KeyCode::U, Key::U),
+        (KeyCode::V, Key::V),
+        (KeyCode::W, Key::W),
+        (KeyCode::X, Key::X),
+        (KeyCode::Y, Key::Y),
+        (KeyCode::Z, Key::Z),
+        (KeyCode::F1, Key::F1),
+        (KeyCode::F2, Key::F2),
+        (KeyCode::F3, Key::F3),
+        (KeyCode::F4, Key::F4),
+        (KeyCode::F5, Key::F5),
+        (KeyCode::F6, Key::F6),
+        (KeyCode::F7, Key::F7),
+        (KeyCode::F8, Key::F8),
+        (KeyCode::F9, Key::F9),
+        (KeyCode::F10, Key::F10),
+        (KeyCode::F11, Key::F11),
+        (KeyCode::F12, Key::F12),
+        (KeyCode::F13, Key::F13),
+        (KeyCode::F14, Key::F14),
+        (KeyCode::F15, Key::F15),
+        (KeyCode::F16, Key::F16),
+        (KeyCode::F17, Key::F17),
+        (KeyCode::F18, Key::F18),
+        (KeyCode::F19, Key::F19),
+        (KeyCode::F20, Key::F20),
+        (KeyCode::F21, Key::F21),
+        (KeyCode::F22, Key::F22),
+        (KeyCode::F23, Key::F23),
+        (KeyCode::F24, Key::F24),
+        (KeyCode::BrowserBack, Key::BrowserBack),
+    ];
+
+    impl TryFrom<KeyCode> for Key {
+        type Error = ConversionError;
+
+        fn try_from(key: KeyCode) -> Result<Self, Self::Error> {
+            lookup_right(KEYS, key).ok_or(ConversionError::UnmappedKey(key))
+        }
+    }
+
+    impl TryFrom<Key> for KeyCode {
+        type Error = ConversionError;
+
+        fn try_from(key: Key) -> Result<Self, Self::Error> {
+            lookup_left(KEYS, &key)
+                .ok_or_else(|| ConversionError::UnmappedForeignKey(format!("{:?}", key)))
+        }
+    }
+
+    impl Modifiers {
+        /// egui modifiers as they'd be reported on `platform`. egui only has a Super/Cmd
+        /// modifier on macOS, so `SUPER` elsewhere has no counterpart.
+        pub fn to_egui(self, platform: Platform) -> Result<egui::Modifiers, ConversionError> {
+            let mac = platform == Platform::MacOs;
+            if self.is_special_input() || (self.contains(Modifiers::SUPER) && !mac) {
+                return Err(ConversionError::UnmappedModifiers(self));
+            }
+            let ctrl = self.contains(Modifiers::CONTROL);
+            let mac_cmd = self.contains(Modifiers::SUPER);
+            Ok(egui::Modifiers {
+                alt: self.contains(Modifiers::ALT),
+                ctrl,
+                shift: self.contains(Modifiers::SHIFT),
+                mac_cmd,
+                command: if mac { mac_cmd } else { ctrl },
+            })
+        }
+
+        /// Modifiers from egui's as reported on `platform`. `command` only matters when
+        /// neither `ctrl` nor `mac_cmd` is set, as in hand-built shortcuts like
+        /// `egui::Modifiers::COMMAND`.
+        pub fn from_egui(modifiers: egui::Modifiers, platform: Platform) -> Self {
+            let mut result = Modifiers::empty();
+            result.set(Modifiers::ALT, modifiers.alt);
+            result.set(Modifiers::SHIFT, modifiers.shift);
+            result.set(Modifiers::CONTROL, modifiers.ctrl);
+            result.set(Modifiers::SUPER, modifiers.mac_cmd);
+            if modifiers.command && !modifiers.ctrl && !modifiers.mac_cmd {
+                if platform == Platform::MacOs {
+                    result |= Modifiers::SUPER;
+                } else {
+                    result |= Modifiers::CONTROL;
+                }
+            }
+            result
+        }
+    }
+
+    /// Uses [`Platform::current`]; see [`Modifiers::to_egui`].
+    impl TryFrom<Modifiers> for egui::Modifiers {
+        type Error = ConversionError;
+
+        fn try_from(modifiers: Modifiers) -> Result<Self, Self::Error> {
+            modifiers.to_egui(Platform::current())
+        }
+    }
+
+    /// Uses [`Platform::current`]; see [`Modifiers::from_egui`].
+    impl From<egui::Modifiers> for Modifiers {
+        fn from(modifiers: egui::Modifiers) -> Self {
+            Modifiers::from_egui(modifiers, Platform::current())
+        }
+    }
+
+    impl ReaperActionInput {
+        /// The shortcut as egui sees it on `platform`.
+        pub fn to_egui_shortcut(
+            self,
+            platform: Platform,
+        ) -> Result<egui::KeyboardShortcut, ConversionError> {
+            Ok(egui::KeyboardShortcut::new(
+                self.modifiers.to_egui(platform)?,
+                Key::try_from(self.key)?,
+            ))
+        }
+
+        /// The input for an egui shortcut reported on `platform`.
+        pub fn from_egui_shortcut(
+            shortcut: egui::KeyboardShortcut,
+            platform: Platform,
+        ) -> Result<Self, ConversionError> {
+            Ok(ReaperActionInput {
+                key: KeyCode::try_from(shortcut.logical_key)?,
+                modifiers: Modifiers::from_egui(shortcut.modifiers, platform),
+            })
+        }
+    }
+
+    // A `(Modifiers, KeyCode)` pair converts through `ReaperActionInput`: the orphan rule
+    // rules out `TryFrom` between a tuple and egui's shortcut in either direction.
+
+    /// Uses [`Platform::current`]; see [`ReaperActionInput::to_egui_shortcut`].
+    impl TryFrom<ReaperActionInput> for egui::KeyboardShortcut {
+        type Error = ConversionError;
+
+        fn try_from(input: ReaperActionInput) -> Result<Self, Self::Error> {
+            input.to_egui_shortcut(Platform::current())
+        }
+    }
+
+    /// Uses [`Platform::current`]; see [`ReaperActionInput::from_egui_shortcut`].
+    impl TryFrom<egui::KeyboardShortcut> for ReaperActionInput {
+        type Error = ConversionError;
+
+        fn try_from(shortcut: egui::KeyboardShortcut) -> Result<Self, Self::Error> {
+            ReaperActionInput::from_egui_shortcut(shortcut, Platform::current())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "keyboard-types")]
+    #[test]
+    fn test_keyboard_types_round_trip() {
+        use keyboard_types::Code;
+
+        for &(key, code) in keyboard_types_impl::CODES {
+            assert_eq!(Code::try_from(key), Ok(code), "{:?}", key);
+            assert_eq!(KeyCode::try_from(code), Ok(key), "{:?}", code);
+        }
+
+        for key in [KeyCode::LButton, KeyCode::Shift, KeyCode::Control, KeyCode::Zoom] {
+            assert_eq!(Code::try_from(key), Err(ConversionError::UnmappedKey(key)));
+        }
+        for code in [Code::Fn, Code::F25, Code::NumpadEqual, Code::Unidentified] {
+            assert!(matches!(
+                KeyCode::try_from(code),
+                Err(ConversionError::UnmappedForeignKey(_))
+            ));
+        }
+
+        let ours = Modifiers::SHIFT | Modifiers::SUPER;
+        let theirs = keyboard_types::Modifiers::SHIFT | keyboard_types::Modifiers::META;
+        assert_eq!(keyboard_types::Modifiers::try_from(ours), Ok(theirs));
+        assert_eq!(Modifiers::try_from(theirs), Ok(ours));
+        assert_eq!(
+            Modifiers::try_from(theirs | keyboard_types::Modifiers::CAPS_LOCK),
+            Ok(ours)
+        );
+        assert!(Modifiers::try_from(keyboard_types::Modifiers::ALT_GRAPH).is_err());
+        assert_eq!(
+            keyboard_types::Modifiers::try_from(Modifiers::SPECIAL_INPUT),
+            Err(ConversionError::UnmappedModifiers(Modifiers::SPECIAL_INPUT))
+        );
+    }
+
+    #[cfg(feature = "egui")]
+    #[test]
+    fn test_egui_round_trip() {
+        use crate::action_list::ReaperActionInput;
+        use crate::modifiers::Platform;
+        use egui::Key;
+
+        for &(key, egui_key) in egui_impl::KEYS {
+            assert_eq!(Key::try_from(key), Ok(egui_key), "{:?}", key);
+            assert_eq!(KeyCode::try_from(egui_key), Ok(key), "{:?}", egui_key);
+        }
+
+        for key in [KeyCode::Numpad1, KeyCode::LShift, KeyCode::MediaPlayPause] {
+            assert_eq!(Key::try_from(key), Err(ConversionError::UnmappedKey(key)));
+        }
+        for key in [Key::Copy, Key::Colon, Key::Plus, Key::F25] {
+            assert!(matches!(
+                KeyCode::try_from(key),
+                Err(ConversionError::UnmappedForeignKey(_))
+            ));
+        }
+
+        let input = ReaperActionInput {
+            key: KeyCode::S,
+            modifiers: Modifiers::SUPER | Modifiers::SHIFT,
+        };
+        let shortcut = input.to_egui_shortcut(Platform::MacOs).unwrap();
+        assert_eq!(
+            shortcut,
+            egui::KeyboardShortcut::new(
+                egui::Modifiers::MAC_CMD | egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                Key::S
+            )
+        );
+        assert_eq!(
+            ReaperActionInput::from_egui_shortcut(shortcut, Platform::MacOs),
+            Ok(input)
+        );
+        let pair = (Modifiers::CONTROL, KeyCode::Z);
+        let shortcut = egui::KeyboardShortcut::try_from(ReaperActionInput::from(pair)).unwrap();
+        assert_eq!(shortcut.logical_key, Key::Z);
+        let back: (Modifiers, KeyCode) = ReaperActionInput::try_from(shortcut).unwrap().into();
+        assert_eq!(back, pair);
+
+        // egui has no Windows-key modifier
+        assert_eq!(
+            input.to_egui_shortcut(Platform::Windows),
+            Err(ConversionError::UnmappedModifiers(input.modifiers))
+        );
+
+        let ctrl = Modifiers::CONTROL;
+        let egui_ctrl = ctrl.to_egui(Platform::Linux).unwrap();
+        assert!(egui_ctrl.ctrl && egui_ctrl.command && !egui_ctrl.mac_cmd);
+        assert_eq!(Modifiers::from_egui(egui_ctrl, Platform::Linux), ctrl);
+        assert_eq!(
+            Modifiers::from_egui(egui::Modifiers::COMMAND, Platform::MacOs),
+            Modifiers::SUPER
+        );
+        assert_eq!(
+            Modifiers::from_egui(egui::Modifiers::COMMAND, Platform::Windows),
+            Modifiers::CONTROL
+        );
+        assert!(Modifiers::SPECIAL_INPUT.to_egui(Platform::MacOs).is_err());
+    }
+}
@@ -1,17 +1,46 @@
-// Cargo.toml
-// [dependencies]
-// num_enum = "0.5"
-
-use num_enum::{IntoPrimitive, TryFromPrimitive};
+use crate::action_list::ParseError;
 use serde::{Deserialize, Serialize};
-use std::convert::TryFrom;
+use std::borrow::Cow;
+use std::str::FromStr;
+
+/// Declares [`KeyCode`] from `Name = code` pairs, along with the conversions both ways.
+macro_rules! key_codes {
+    ($($name:ident = $code:literal,)*) => {
+        /// All Win32 virtual‐key codes, with simpler names (no `VK_`).
+        ///
+        /// Codes without a name (REAPER writes character codes above 255 for some keys) are
+        /// kept as [`KeyCode::Other`], so they survive a round trip.
+        #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum KeyCode {
+            $($name,)*
+            /// A code with no named variant. [`KeyCode::from_u16`] never builds this for
+            /// a named code.
+            Other(u16),
+        }
+
+        impl KeyCode {
+            /// Get the raw numeric value.
+            pub fn as_u16(self) -> u16 {
+                match self {
+                    $(KeyCode::$name => $code,)*
+                    KeyCode::Other(code) => code,
+                }
+            }
+
+            /// Every named key, in code order.
+            const NAMED: &[KeyCode] = &[$(KeyCode::$name,)*];
 
-/// All Win32 virtual‐key codes, with simpler names (no `VK_`).
-#[derive(
-    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive,
-)]
-#[repr(u16)]
-pub enum KeyCode {
+            fn named(value: u16) -> Option<Self> {
+                match value {
+                    $($code => Some(KeyCode::$name),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+key_codes! {
     LButton = 0x01,
     RButton = 0x02,
     Cancel = 0x03,
@@ -184,32 +213,76 @@ pub enum KeyCode {
     NoName = 0xFC,
     PA1 = 0xFD,
     ClearKey = 0xFE,
+    // REAPER marks extended keys with 0x8000; numpad Enter is the only one sharing its
+    // virtual-key code with a main-keyboard key
+    NumpadEnter = 0x800D,
 }
 
+/// Printable characters other than letters and digits, with the US-layout key typing them.
+const CHARACTER_KEYS: &[(char, KeyCode)] = &[
+    (' ', KeyCode::Space),
+    (';', KeyCode::OEM1),
+    ('=', KeyCode::OEMPlus),
+    (',', KeyCode::OEMComma),
+    ('-', KeyCode::OEMMinus),
+    ('.', KeyCode::OEMPeriod),
+    ('/', KeyCode::OEM2),
+    ('`', KeyCode::OEM3),
+    ('[', KeyCode::OEM4),
+    ('\\', KeyCode::OEM5),
+    (']', KeyCode::OEM6),
+    ('\'', KeyCode::OEM7),
+];
+
 impl KeyCode {
-    /// Get the raw numeric value.
-    pub fn as_u8(self) -> u8 {
-        self as u8
+    /// The key typing `c` on a US layout: letters (either case), digits, space and the
+    /// unshifted punctuation keys.
+    pub fn from_char(c: char) -> Option<Self> {
+        match c.to_ascii_uppercase() {
+            c @ ('A'..='Z' | '0'..='9') => Self::named(c as u16),
+            c => CHARACTER_KEYS
+                .iter()
+                .find(|(ch, _)| *ch == c)
+                .map(|(_, key)| *key),
+        }
     }
 
-    /// Try to convert from a raw u16 value (with validation).
+    /// Reverse of [`KeyCode::from_char`], with letters in upper case.
+    pub fn to_char(self) -> Option<char> {
+        match (self, self.as_u16()) {
+            (KeyCode::Other(_), _) => None,
+            (_, code @ (0x30..=0x39 | 0x41..=0x5A)) => char::from_u32(code as u32),
+            _ => CHARACTER_KEYS
+                .iter()
+                .find(|(_, key)| *key == self)
+                .map(|(ch, _)| *ch),
+        }
+    }
+
+    /// The raw value as a byte, or `None` for codes above 255; see [`KeyCode::as_u16`].
+    pub fn as_u8(self) -> Option<u8> {
+        u8::try_from(self.as_u16()).ok()
+    }
+
+    /// Convert from a raw value, falling back to [`KeyCode::Other`] for codes without a
+    /// name. Only 0, which isn't a key, is rejected.
     pub fn from_u16(value: u16) -> Option<Self> {
-        if value <= 255 {
-            Self::from_u8(value as u8)
-        } else {
-            None
+        match value {
+            0 => None,
+            _ => Some(Self::named(value).unwrap_or(KeyCode::Other(value))),
         }
     }
 
     /// Try to convert from a raw u8 value.
     pub fn from_u8(value: u8) -> Option<Self> {
-        Self::try_from(value as u16).ok()
+        Self::from_u16(value as u16)
     }
 
-    /// Get human-readable display name for comments
-    pub fn display_name(self) -> &'static str {
+    /// Get human-readable display name for comments, `"Key(<code>)"` for codes without
+    /// a name.
+    pub fn display_name(self) -> Cow<'static, str> {
         use KeyCode::*;
-        match self {
+        let name = match self {
             Backspace => "Backspace",
             Tab => "Tab",
             Enter => "Enter",
@@ -276,6 +349,13 @@ impl KeyCode {
             Numpad7 => "Numpad7",
             Numpad8 => "Numpad8",
             Numpad9 => "Numpad9",
+            Multiply => "NumpadMultiply",
+            Add => "NumpadAdd",
+            Separator => "NumpadSeparator",
+            Subtract => "NumpadSubtract",
+            Decimal => "NumpadDecimal",
+            Divide => "NumpadDivide",
+            NumpadEnter => "NumpadEnter",
             F1 => "F1",
             F2 => "F2",
             F3 => "F3",
@@ -288,6 +368,18 @@ impl KeyCode {
             F10 => "F10",
             F11 => "F11",
             F12 => "F12",
+            F13 => "F13",
+            F14 => "F14",
+            F15 => "F15",
+            F16 => "F16",
+            F17 => "F17",
+            F18 => "F18",
+            F19 => "F19",
+            F20 => "F20",
+            F21 => "F21",
+            F22 => "F22",
+            F23 => "F23",
+            F24 => "F24",
             OEM1 => ";",
             OEMPlus => "=",
             OEMComma => ",",
@@ -299,24 +391,127 @@ impl KeyCode {
             OEM5 => "\\",
             OEM6 => "]",
             OEM7 => "'",
+            Other(code) => return Cow::Owned(format!("Key({})", code)),
             _ => "Unknown",
+        };
+        Cow::Borrowed(name)
+    }
+}
+
+/// Parses a raw code (`"65"`) or, failing that, a display name (`"A"`, `"F5"`, `"Enter"`
+/// or its macOS name `"Return"`), ignoring case. Digits are raw codes first, so `"5"` is
+/// code 5, while `"0"`, which isn't a code, is the 0 key.
+impl FromStr for KeyCode {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim();
+        if let Some(key) = text.parse::<u16>().ok().and_then(KeyCode::from_u16) {
+            return Ok(key);
+        }
+        if text.eq_ignore_ascii_case("Return") {
+            return Ok(KeyCode::Enter);
         }
+        KeyCode::NAMED
+            .iter()
+            .copied()
+            .find(|key| {
+                let name = key.display_name();
+                name != "Unknown" && name.eq_ignore_ascii_case(text)
+            })
+            .ok_or_else(|| ParseError::InvalidKeyName(s.to_string()))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::action_list::ParseError;
     use crate::keycodes::KeyCode;
     #[test]
     fn test_w() {
         let w = KeyCode::W.as_u8();
-        println!("{}", w);
-        assert_eq!(w, 87);
+        println!("{:?}", w);
+        assert_eq!(w, Some(87));
     }
 
     #[test]
     fn test_u8_to_keycode() {
         KeyCode::from_u16(87);
-        assert_eq!(KeyCode::W.as_u8(), 87);
+        assert_eq!(KeyCode::W.as_u8(), Some(87));
+    }
+
+    #[test]
+    fn test_unnamed_codes_keep_their_value() {
+        assert_eq!(KeyCode::from_u16(0x57), Some(KeyCode::W));
+        assert_eq!(KeyCode::from_u16(0x07), Some(KeyCode::Other(0x07)));
+        assert_eq!(KeyCode::from_u16(12520), Some(KeyCode::Other(12520)));
+        assert_eq!(KeyCode::Other(12520).as_u16(), 12520);
+        assert_eq!(KeyCode::Other(12520).as_u8(), None);
+        assert_eq!(KeyCode::Other(203).as_u8(), Some(203));
+        assert_eq!(KeyCode::Other(203).display_name(), "Key(203)");
+        assert_eq!(KeyCode::W.display_name(), "W");
+        assert_eq!(KeyCode::from_u16(0), None);
+    }
+
+    #[test]
+    fn test_extended_function_keys() {
+        for (n, code) in (13..=24).zip(0x7C..=0x87u16) {
+            let key = KeyCode::from_u16(code).unwrap();
+            assert!(!matches!(key, KeyCode::Other(_)));
+            assert_eq!(key.as_u16(), code);
+            assert_eq!(key.as_u8().map(u16::from), Some(code));
+            assert_eq!(key.display_name(), format!("F{}", n));
+            assert_eq!(format!("f{}", n).parse::<KeyCode>().ok(), Some(key));
+        }
+        assert_eq!(KeyCode::F13.as_u16(), 0x7C);
+        assert_eq!(KeyCode::F24.as_u16(), 0x87);
+    }
+
+    #[test]
+    fn test_parse_from_str() {
+        let cases = [
+            // Raw codes
+            ("65", KeyCode::A),
+            ("12520", KeyCode::Other(12520)),
+            ("5", KeyCode::XButton1),
+            // Display names, in any case
+            ("A", KeyCode::A),
+            ("f5", KeyCode::F5),
+            ("PageDown", KeyCode::PageDown),
+            ("0", KeyCode::Key0),
+            ("\\", KeyCode::OEM5),
+            // Aliases
+            ("Return", KeyCode::Enter),
+            ("enter", KeyCode::Enter),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(text.parse::<KeyCode>().ok(), Some(expected), "{:?}", text);
+        }
+
+        for bad in ["", "Unknown", "F25", "Ctrl+A", "70000"] {
+            let err = bad.parse::<KeyCode>().unwrap_err();
+            assert!(matches!(&err, ParseError::InvalidKeyName(name) if name == bad), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_char_conversions() {
+        assert_eq!(KeyCode::from_char('a'), Some(KeyCode::A));
+        assert_eq!(KeyCode::from_char('Z'), Some(KeyCode::Z));
+        assert_eq!(KeyCode::from_char('0'), Some(KeyCode::Key0));
+        assert_eq!(KeyCode::from_char('9'), Some(KeyCode::Key9));
+        assert_eq!(KeyCode::from_char('\\'), Some(KeyCode::OEM5));
+        assert_eq!(KeyCode::from_char(' '), Some(KeyCode::Space));
+        for c in ['é', '!', '\n', '+', '@'] {
+            assert_eq!(KeyCode::from_char(c), None, "{:?}", c);
+        }
+
+        for c in ('A'..='Z').chain('0'..='9').chain(" ;=,-./`[\\]'".chars()) {
+            let key = KeyCode::from_char(c).unwrap();
+            assert_eq!(key.to_char(), Some(c), "{:?}", key);
+        }
+        assert_eq!(KeyCode::from_char('q').and_then(KeyCode::to_char), Some('Q'));
+        assert_eq!(KeyCode::F1.to_char(), None);
+        assert_eq!(KeyCode::Other(0x41).to_char(), None);
     }
 }
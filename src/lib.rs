@@ -7,9 +7,28 @@ pub mod keycodes;
 
 pub mod special_inputs;
 
+pub mod midi_inputs;
+
 pub mod action_list;
+pub use action_list::entries;
+
+pub mod query;
+
+pub mod store;
+
+#[cfg(feature = "test-util")]
+pub mod testing;
 
 pub mod sections;
 
+#[cfg(feature = "bundle")]
+pub mod bundle;
+
+#[cfg(any(feature = "keyboard-types", feature = "egui"))]
+pub mod interop;
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impls;
+
 pub mod action_configs;
 pub use action_configs::get_action_list_from_current_config;
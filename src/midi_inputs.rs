@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A MIDI message learned as a shortcut (control surfaces, pad controllers).
+///
+/// Reaper stores these as KEY lines whose modifier field is the MIDI status byte and whose
+/// key code is the first data byte:
+///
+/// | modifier field | message        | key code field  |
+/// |----------------|----------------|-----------------|
+/// | `144..=159`    | note on, ch 1–16 | note number   |
+/// | `176..=191`    | CC, ch 1–16    | controller      |
+/// | `192..=207`    | program change, ch 1–16 | program |
+///
+/// e.g. `KEY 176 64 40044 0` is CC 64 (sustain) on channel 1, shown as `MIDI Chan 1 CC 64`
+/// like REAPER does in its comments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct MidiInput {
+    /// Zero-based channel (0 is shown as "Chan 1").
+    pub channel: u8,
+    pub kind: MidiKind,
+}
+
+/// The kind of MIDI message and its number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum MidiKind {
+    Cc(u8),
+    Note(u8),
+    Pc(u8),
+}
+
+impl MidiInput {
+    /// Decode the modifier and key code fields of a KEY line, if they hold a MIDI message.
+    pub fn from_fields(status: u8, data: u16) -> Option<Self> {
+        let data = u8::try_from(data).ok().filter(|d| *d < 128)?;
+        let kind = match status & 0xF0 {
+            0x90 => MidiKind::Note(data),
+            0xB0 => MidiKind::Cc(data),
+            0xC0 => MidiKind::Pc(data),
+            _ => return None,
+        };
+        Some(MidiInput {
+            channel: status & 0x0F,
+            kind,
+        })
+    }
+
+    /// The value written in the modifier field: the MIDI status byte.
+    pub fn status_byte(self) -> u8 {
+        let message = match self.kind {
+            MidiKind::Note(_) => 0x90,
+            MidiKind::Cc(_) => 0xB0,
+            MidiKind::Pc(_) => 0xC0,
+        };
+        message | (self.channel & 0x0F)
+    }
+
+    /// The value written in the key code field: the note, controller or program number.
+    pub fn data_byte(self) -> u8 {
+        match self.kind {
+            MidiKind::Cc(n) | MidiKind::Note(n) | MidiKind::Pc(n) => n,
+        }
+    }
+}
+
+impl fmt::Display for MidiInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, number) = match self.kind {
+            MidiKind::Cc(n) => ("CC", n),
+            MidiKind::Note(n) => ("Note", n),
+            MidiKind::Pc(n) => ("PC", n),
+        };
+        write!(f, "MIDI Chan {} {} {}", self.channel + 1, kind, number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_midi_fields_round_trip() {
+        let cases = [
+            (176, 64, MidiInput { channel: 0, kind: MidiKind::Cc(64) }, "MIDI Chan 1 CC 64"),
+            (144, 60, MidiInput { channel: 0, kind: MidiKind::Note(60) }, "MIDI Chan 1 Note 60"),
+            (201, 5, MidiInput { channel: 9, kind: MidiKind::Pc(5) }, "MIDI Chan 10 PC 5"),
+        ];
+        for (status, data, input, name) in cases {
+            assert_eq!(MidiInput::from_fields(status, data), Some(input));
+            assert_eq!(input.status_byte(), status);
+            assert_eq!(input.data_byte() as u16, data);
+            assert_eq!(input.to_string(), name);
+        }
+
+        // Not a MIDI status byte, or a data byte out of range
+        assert_eq!(MidiInput::from_fields(255, 248), None);
+        assert_eq!(MidiInput::from_fields(33, 77), None);
+        assert_eq!(MidiInput::from_fields(176, 128), None);
+    }
+}
@@ -1,52 +1,137 @@
+use crate::action_list::ParseError;
 use bitflags::bitflags;
 use serde::{Deserialize, Serialize};
 
 bitflags! {
-    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
-    pub struct Modifiers: u8 {
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+    pub struct Modifiers: u16 {
+        /// The key code is a character rather than a virtual key. REAPER writes these
+        /// with the lowest bit of the modifier code cleared (e.g. 0 for a bare character).
+        const CHARACTER = 0b0000_0001; //  1
         const SHIFT   = 0b0000_0100; //  4
         //
         const CONTROL = 0b0010_0000; // 32
         const ALT     = 0b0001_0000; // 16
         const SUPER   = 0b0000_1000; //  8
         
-        // Special flag for modifier code 255 (mousewheel, multitouch, media keys)
-        const SPECIAL_INPUT = 0b1000_0000; // 128 - highest bit to avoid conflicts
+        // Special flag for modifier code 255 (mousewheel, multitouch, media keys), outside
+        // the byte the other codes map onto. This was 128 while `Modifiers` was a `u8`
+        const SPECIAL_INPUT = 0b1_0000_0000; // 256
     }
 }
 
 impl Modifiers {
-    /// The Reaper Keymap code for modifiers is always 1 + the sum of the bits, this is because
-    /// no modifiers is 1 instead of 0 in the ReaperKeyMap files
-    /// 
+    /// The Reaper Keymap code for modifiers is 1 + the sum of the bits, this is because
+    /// no modifiers is 1 instead of 0 in the ReaperKeyMap files. The lowest bit is really
+    /// REAPER's virtual-key flag, so [`Modifiers::CHARACTER`] clears it instead.
+    ///
+    /// Bits without a named flag are written back as they were read.
+    ///
     /// Special case: modifier code 255 represents special inputs (mousewheel, multitouch, etc.)
     pub fn reaper_code(self) -> u8 {
         if self.contains(Modifiers::SPECIAL_INPUT) {
             255
         } else {
-            1 + (self.bits() & 0x7F) // Mask out the special bit for normal modifiers
+            (self.bits() & 0xFF) as u8 ^ 1
+        }
+    }
+}
+
+/// Operating system family a keymap is used on.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Platform {
+    MacOs,
+    Windows,
+    Linux,
+}
+
+impl Platform {
+    /// The platform this crate was compiled for.
+    pub fn current() -> Self {
+        if cfg!(target_os = "macos") {
+            Platform::MacOs
+        } else if cfg!(target_os = "windows") {
+            Platform::Windows
+        } else {
+            Platform::Linux
         }
     }
 }
 
 // Helper to convert raw modifier code into Modifiers
 impl Modifiers {
-    /// Convert Reaper code (1 + bits) back into flag set.
+    /// Convert Reaper code (1 + bits) back into flag set, keeping bits without a named
+    /// flag so [`Modifiers::reaper_code`] gives the same code back.
     /// Special handling for code 255 which represents special inputs like mousewheel.
-    pub fn try_from_reaper_code(n: u8) -> Option<Self> {
+    pub fn from_reaper_code(n: u8) -> Self {
         if n == 255 {
             // Special case: modifier 255 represents mousewheel, multitouch, media keys
-            Some(Modifiers::SPECIAL_INPUT)
+            Modifiers::SPECIAL_INPUT
         } else {
-            let bits = n.checked_sub(1)?;
-            Modifiers::from_bits(bits)
+            Modifiers::from_bits_retain((n ^ 1) as u16)
         }
     }
-    
+
+    /// [`Modifiers::from_reaper_code`]; every code converts.
+    pub fn try_from_reaper_code(n: u8) -> Option<Self> {
+        Some(Self::from_reaper_code(n))
+    }
+
+    /// Check if the key code is a character rather than a virtual key.
+    pub fn is_character(self) -> bool {
+        self.contains(Modifiers::CHARACTER)
+    }
+
     /// Check if this represents a special input type (mousewheel, multitouch, etc.)
     pub fn is_special_input(self) -> bool {
         self.contains(Modifiers::SPECIAL_INPUT)
     }
+
+    /// The names of the set modifiers as shown on `platform`, in the order they're
+    /// written: "Cmd", "Opt", "Shift", "Control" on macOS, "Ctrl", "Alt", "Shift", "Win"
+    /// on Windows ("Super" on Linux).
+    pub fn display_parts(self, platform: Platform) -> Vec<&'static str> {
+        let names: [(Modifiers, &'static str); 4] = match platform {
+            Platform::MacOs => [
+                (Modifiers::SUPER, "Cmd"),
+                (Modifiers::ALT, "Opt"),
+                (Modifiers::SHIFT, "Shift"),
+                (Modifiers::CONTROL, "Control"),
+            ],
+            Platform::Windows | Platform::Linux => [
+                (Modifiers::CONTROL, "Ctrl"),
+                (Modifiers::ALT, "Alt"),
+                (Modifiers::SHIFT, "Shift"),
+                (Modifiers::SUPER, if platform == Platform::Windows { "Win" } else { "Super" }),
+            ],
+        };
+        names
+            .into_iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| name)
+            .collect()
+    }
+
+    /// Parse `+`-separated modifier words as written in comments ("Cmd+Shift"), in any
+    /// order and case. "Cmd", "Super" and "Win" are [`Modifiers::SUPER`], "Opt" and "Alt"
+    /// [`Modifiers::ALT`], "Ctrl" and "Control" [`Modifiers::CONTROL`]. An empty string
+    /// is no modifiers.
+    pub fn from_display_string(s: &str) -> Result<Self, ParseError> {
+        let mut modifiers = Modifiers::empty();
+        if s.trim().is_empty() {
+            return Ok(modifiers);
+        }
+        for word in s.split('+').map(str::trim) {
+            modifiers |= match word.to_ascii_lowercase().as_str() {
+                "cmd" | "super" | "win" => Modifiers::SUPER,
+                "opt" | "alt" => Modifiers::ALT,
+                "ctrl" | "control" => Modifiers::CONTROL,
+                "shift" => Modifiers::SHIFT,
+                _ => return Err(ParseError::InvalidModifierName(word.to_string())),
+            };
+        }
+        Ok(modifiers)
+    }
 }
 #[cfg(test)]
 mod tests {
@@ -88,14 +173,15 @@ mod tests {
 
         for bits in 0..=max_regular_bits {
             if let Some(flags) = Modifiers::from_bits(bits) {
-                // Skip if this includes SPECIAL_INPUT
-                if flags.contains(Modifiers::SPECIAL_INPUT) {
+                // Skip if this includes SPECIAL_INPUT or CHARACTER, see
+                // test_every_reaper_code_round_trips
+                if flags.intersects(Modifiers::SPECIAL_INPUT | Modifiers::CHARACTER) {
                     continue;
                 }
                 
                 // 1) reaper_code must be exactly sum_of_bits + 1 for regular flags
                 assert_eq!(
-                    flags.reaper_code(),
+                    flags.reaper_code() as u16,
                     bits + 1,
                     "flags={:?} had bits={} but reaper_code() was {}",
                     flags,
@@ -176,4 +262,77 @@ mod tests {
         assert!(!normal.is_special_input());
         assert_ne!(normal.reaper_code(), 255);
     }
+
+    #[test]
+    fn test_from_display_string() {
+        let cases = [
+            ("Cmd+Shift", Modifiers::SUPER | Modifiers::SHIFT),
+            ("Control+Alt", Modifiers::CONTROL | Modifiers::ALT),
+            ("ctrl + opt + SHIFT", Modifiers::CONTROL | Modifiers::ALT | Modifiers::SHIFT),
+            ("Win", Modifiers::SUPER),
+            ("Super+Cmd", Modifiers::SUPER),
+            ("", Modifiers::empty()),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(Modifiers::from_display_string(text).unwrap(), expected, "{:?}", text);
+        }
+
+        for (text, word) in [("Cmd+M", "M"), ("Shift+", ""), ("Hyper", "Hyper")] {
+            let err = Modifiers::from_display_string(text).unwrap_err();
+            assert!(matches!(&err, ParseError::InvalidModifierName(w) if w == word), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_display_parts() {
+        let all = Modifiers::SHIFT | Modifiers::CONTROL | Modifiers::ALT | Modifiers::SUPER;
+        assert_eq!(all.display_parts(Platform::MacOs), ["Cmd", "Opt", "Shift", "Control"]);
+        assert_eq!(all.display_parts(Platform::Windows), ["Ctrl", "Alt", "Shift", "Win"]);
+        assert_eq!(all.display_parts(Platform::Linux), ["Ctrl", "Alt", "Shift", "Super"]);
+        assert_eq!((Modifiers::SUPER | Modifiers::SHIFT).display_parts(Platform::MacOs), ["Cmd", "Shift"]);
+        assert!(Modifiers::empty().display_parts(Platform::Windows).is_empty());
+        assert!(Modifiers::SPECIAL_INPUT.display_parts(Platform::MacOs).is_empty());
+
+        // Every platform's names parse back
+        for platform in [Platform::MacOs, Platform::Windows, Platform::Linux] {
+            let text = all.display_parts(platform).join("+");
+            assert_eq!(Modifiers::from_display_string(&text).unwrap(), all, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_every_reaper_code_round_trips() {
+        let named = Modifiers::SHIFT | Modifiers::CONTROL | Modifiers::ALT | Modifiers::SUPER;
+        for code in 0..=255u8 {
+            let modifiers = Modifiers::from_reaper_code(code);
+            assert_eq!(Modifiers::try_from_reaper_code(code), Some(modifiers));
+            assert_eq!(modifiers.reaper_code(), code, "code {} read as {:?}", code, modifiers);
+            match code {
+                // Mousewheel, multitouch, media keys
+                255 => assert_eq!(modifiers, Modifiers::SPECIAL_INPUT),
+                // Virtual-key bit clear: the key code is a character
+                c if c & 1 == 0 => {
+                    assert!(modifiers.is_character() && !modifiers.is_special_input())
+                }
+                // A virtual key with a set of flags; codes made only of named flags are the
+                // classic 1 + bits
+                c => {
+                    assert!(!modifiers.is_character() && !modifiers.is_special_input());
+                    if named.contains(modifiers) {
+                        assert_eq!(modifiers.bits() + 1, c as u16);
+                    }
+                }
+            }
+        }
+
+        // Codes seen in real keymaps
+        assert_eq!(Modifiers::from_reaper_code(0), Modifiers::CHARACTER);
+        assert_eq!(
+            Modifiers::from_reaper_code(24),
+            Modifiers::CHARACTER | Modifiers::ALT | Modifiers::SUPER
+        );
+        assert_eq!(Modifiers::from_reaper_code(3).bits(), 0b10);
+        assert_eq!(Modifiers::from_reaper_code(2).bits(), 0b11);
+        assert_eq!(Modifiers::from_reaper_code(129).bits(), 0b1000_0000);
+    }
 }
@@ -0,0 +1,372 @@
+//! Filtering entries of a [`ReaperActionList`], either with a fixed [`KeymapQuery`] or by
+//! chaining lazy adapters from [`ReaperActionList::query`]:
+//!
+//! ```ignore
+//! let shifted: Vec<&str> = list
+//!     .query()
+//!     .keys()
+//!     .in_section(ReaperActionSection::MidiEditor)
+//!     .with_modifier(Modifiers::SHIFT)
+//!     .map_command_ids()
+//!     .collect();
+//! ```
+//!
+//! Both are built on the same [`Predicate`]s, so a filter means the same thing either way.
+
+use crate::action_list::{EntryKind, KeyEntry, KeyInputType, ReaperActionList, ReaperEntry};
+use crate::modifiers::Modifiers;
+use crate::sections::ReaperActionSection;
+
+/// Something a query can filter: a whole entry or a KEY entry. Raw entries have no
+/// section or command id.
+pub trait QueryItem {
+    fn kind(&self) -> EntryKind;
+    fn section(&self) -> Option<ReaperActionSection>;
+    fn command_id(&self) -> Option<&str>;
+    /// The KEY entry, for predicates that only apply to bindings.
+    fn as_key(&self) -> Option<&KeyEntry>;
+}
+
+impl QueryItem for ReaperEntry {
+    fn kind(&self) -> EntryKind {
+        ReaperEntry::kind(self)
+    }
+
+    fn section(&self) -> Option<ReaperActionSection> {
+        ReaperEntry::section(self)
+    }
+
+    fn command_id(&self) -> Option<&str> {
+        ReaperEntry::command_id(self)
+    }
+
+    fn as_key(&self) -> Option<&KeyEntry> {
+        ReaperEntry::as_key(self)
+    }
+}
+
+impl QueryItem for KeyEntry {
+    fn kind(&self) -> EntryKind {
+        EntryKind::Key
+    }
+
+    fn section(&self) -> Option<ReaperActionSection> {
+        Some(self.section)
+    }
+
+    fn command_id(&self) -> Option<&str> {
+        Some(&self.command_id)
+    }
+
+    fn as_key(&self) -> Option<&KeyEntry> {
+        Some(self)
+    }
+}
+
+/// One condition on an entry. Predicates about bindings never match SCR or ACT entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Predicate {
+    Kind(EntryKind),
+    Section(ReaperActionSection),
+    CommandId(String),
+    /// The binding's modifiers include all of these.
+    Modifier(Modifiers),
+    /// The binding is a special input (mousewheel, multitouch, ...).
+    SpecialInput,
+    /// The binding has a parsed comment.
+    Commented,
+    /// The binding's comment marks a MIDI CC relative/mousewheel action.
+    MidiRelative,
+}
+
+impl Predicate {
+    pub fn matches<T: QueryItem + ?Sized>(&self, item: &T) -> bool {
+        match self {
+            Predicate::Kind(kind) => item.kind() == *kind,
+            Predicate::Section(section) => item.section() == Some(*section),
+            Predicate::CommandId(id) => item.command_id() == Some(id.as_str()),
+            Predicate::Modifier(modifiers) => {
+                item.as_key().is_some_and(|k| k.modifiers.contains(*modifiers))
+            }
+            Predicate::SpecialInput => item
+                .as_key()
+                .is_some_and(|k| matches!(k.key_input, KeyInputType::Special(_))),
+            Predicate::Commented => item.as_key().is_some_and(|k| k.comment.is_some()),
+            Predicate::MidiRelative => item
+                .as_key()
+                .and_then(|k| k.comment.as_ref())
+                .is_some_and(|c| c.is_midi_relative),
+        }
+    }
+}
+
+/// A fixed set of filters; an entry matches when every set filter does.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeymapQuery {
+    pub kind: Option<EntryKind>,
+    pub section: Option<ReaperActionSection>,
+    pub command_id: Option<String>,
+    pub modifier: Option<Modifiers>,
+}
+
+impl KeymapQuery {
+    fn predicates(&self) -> Vec<Predicate> {
+        [
+            self.kind.map(Predicate::Kind),
+            self.section.map(Predicate::Section),
+            self.command_id.clone().map(Predicate::CommandId),
+            self.modifier.map(Predicate::Modifier),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    pub fn matches(&self, entry: &ReaperEntry) -> bool {
+        self.predicates().iter().all(|p| p.matches(entry))
+    }
+
+    /// Every entry of `list` matching the query, in file order.
+    pub fn run<'a>(&self, list: &'a ReaperActionList) -> Vec<&'a ReaperEntry> {
+        let predicates = self.predicates();
+        list.entries
+            .iter()
+            .filter(|entry| predicates.iter().all(|p| p.matches(*entry)))
+            .collect()
+    }
+}
+
+/// A lazy chain of filters over entries, started with [`ReaperActionList::query`]. It is
+/// an `Iterator` itself, so any iterator method works at the end of a chain.
+#[derive(Debug, Clone)]
+pub struct Query<I>(I);
+
+/// Keeps the items matching a [`Predicate`].
+#[derive(Debug, Clone)]
+pub struct Filter<I> {
+    inner: I,
+    predicate: Predicate,
+}
+
+/// Narrows entries to their KEY entries.
+#[derive(Debug, Clone)]
+pub struct Keys<I>(I);
+
+/// Maps items to their command ids, skipping raw entries.
+#[derive(Debug, Clone)]
+pub struct CommandIds<I>(I);
+
+impl<'a, T: QueryItem + 'a, I: Iterator<Item = &'a T>> Iterator for Query<I> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl<'a, T: QueryItem + 'a, I: Iterator<Item = &'a T>> Iterator for Filter<I> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let predicate = &self.predicate;
+        self.inner.find(|item| predicate.matches(*item))
+    }
+}
+
+impl<'a, I: Iterator<Item = &'a ReaperEntry>> Iterator for Keys<I> {
+    type Item = &'a KeyEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.find_map(ReaperEntry::as_key)
+    }
+}
+
+impl<'a, T: QueryItem + 'a, I: Iterator<Item = &'a T>> Iterator for CommandIds<I> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.find_map(QueryItem::command_id)
+    }
+}
+
+impl<'a, I: Iterator<Item = &'a ReaperEntry>> Query<I> {
+    /// Only KEY entries, as [`KeyEntry`]s.
+    pub fn keys(self) -> Query<Keys<I>> {
+        Query(Keys(self.0))
+    }
+}
+
+impl<'a, T: QueryItem + 'a, I: Iterator<Item = &'a T>> Query<I> {
+    /// Keep items matching `predicate`.
+    pub fn filter_by(self, predicate: Predicate) -> Query<Filter<I>> {
+        Query(Filter {
+            inner: self.0,
+            predicate,
+        })
+    }
+
+    pub fn of_kind(self, kind: EntryKind) -> Query<Filter<I>> {
+        self.filter_by(Predicate::Kind(kind))
+    }
+
+    pub fn in_section(self, section: ReaperActionSection) -> Query<Filter<I>> {
+        self.filter_by(Predicate::Section(section))
+    }
+
+    pub fn with_command_id(self, command_id: &str) -> Query<Filter<I>> {
+        self.filter_by(Predicate::CommandId(command_id.to_string()))
+    }
+
+    /// Bindings whose modifiers include `modifiers`.
+    pub fn with_modifier(self, modifiers: Modifiers) -> Query<Filter<I>> {
+        self.filter_by(Predicate::Modifier(modifiers))
+    }
+
+    pub fn special_inputs(self) -> Query<Filter<I>> {
+        self.filter_by(Predicate::SpecialInput)
+    }
+
+    pub fn commented(self) -> Query<Filter<I>> {
+        self.filter_by(Predicate::Commented)
+    }
+
+    pub fn midi_relative(self) -> Query<Filter<I>> {
+        self.filter_by(Predicate::MidiRelative)
+    }
+
+    pub fn map_command_ids(self) -> CommandIds<I> {
+        CommandIds(self.0)
+    }
+
+    pub fn collect_entries(self) -> Vec<&'a T> {
+        self.0.collect()
+    }
+
+    /// Whether anything matches. Shadows `Iterator::any`, which is still there as
+    /// `Iterator::any(&mut query, f)`.
+    pub fn any(mut self) -> bool {
+        self.0.next().is_some()
+    }
+
+    pub fn first(mut self) -> Option<&'a T> {
+        self.0.next()
+    }
+}
+
+impl ReaperActionList {
+    /// Start a lazy query over all entries.
+    pub fn query(&self) -> Query<std::slice::Iter<'_, ReaperEntry>> {
+        Query(self.entries.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn test_list() -> ReaperActionList {
+        ReaperActionList::load_from_file("resources/test-file.reaperkeymap").unwrap()
+    }
+
+    #[test]
+    fn test_query_mirrors_filter_loops() {
+        let list = test_list();
+
+        // Special input count
+        let special_input_count = list
+            .entries
+            .iter()
+            .filter(|entry| {
+                if let ReaperEntry::Key(key_entry) = entry {
+                    matches!(key_entry.key_input, KeyInputType::Special(_))
+                } else {
+                    false
+                }
+            })
+            .count();
+        assert!(special_input_count > 0);
+        assert_eq!(list.query().keys().special_inputs().count(), special_input_count);
+
+        // Section distribution of KEY entries
+        let mut section_counts = HashMap::new();
+        for entry in &list.entries {
+            if let ReaperEntry::Key(key_entry) = entry {
+                *section_counts.entry(key_entry.section).or_insert(0) += 1;
+            }
+        }
+        for (section, count) in &section_counts {
+            assert_eq!(list.query().keys().in_section(*section).count(), *count);
+        }
+
+        // Entries with comments
+        let entries_with_comments = list
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                if let ReaperEntry::Key(key_entry) = entry {
+                    key_entry.comment.as_ref()
+                } else {
+                    None
+                }
+            })
+            .count();
+        assert_eq!(list.query().commented().count(), entries_with_comments);
+
+        // MIDI relative actions
+        let midi_relative: Vec<&str> = list
+            .entries
+            .iter()
+            .filter_map(|entry| match entry {
+                ReaperEntry::Key(k) if k.comment.as_ref().is_some_and(|c| c.is_midi_relative) => {
+                    Some(k.command_id.as_str())
+                }
+                _ => None,
+            })
+            .collect();
+        assert!(!midi_relative.is_empty());
+        assert_eq!(
+            list.query().midi_relative().map_command_ids().collect::<Vec<_>>(),
+            midi_relative
+        );
+    }
+
+    #[test]
+    fn test_query_chain_matches_keymap_query() {
+        let list = test_list();
+        let fixed = KeymapQuery {
+            kind: Some(EntryKind::Key),
+            section: Some(ReaperActionSection::MidiEditor),
+            modifier: Some(Modifiers::SHIFT),
+            ..KeymapQuery::default()
+        };
+        let expected: Vec<&str> = fixed
+            .run(&list)
+            .into_iter()
+            .filter_map(QueryItem::command_id)
+            .collect();
+        assert!(!expected.is_empty());
+
+        let chained: Vec<&str> = list
+            .query()
+            .keys()
+            .in_section(ReaperActionSection::MidiEditor)
+            .with_modifier(Modifiers::SHIFT)
+            .map_command_ids()
+            .collect();
+        assert_eq!(chained, expected);
+
+        let first = list
+            .query()
+            .of_kind(EntryKind::Key)
+            .in_section(ReaperActionSection::MidiEditor)
+            .first();
+        assert_eq!(first, list.entries_for_section(ReaperActionSection::MidiEditor).next());
+        assert!(list.query().with_command_id(expected[0]).any());
+        assert!(!list.query().with_command_id("no such command").any());
+        assert_eq!(
+            list.query().keys().collect_entries().len(),
+            list.counts().keys
+        );
+    }
+}
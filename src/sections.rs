@@ -1,11 +1,16 @@
-use num_enum::{IntoPrimitive, TryFromPrimitive};
+use crate::action_list::ParseError;
+use num_enum::{FromPrimitive, IntoPrimitive};
 use serde::{Deserialize, Serialize};
-use std::convert::TryFrom;
+use std::borrow::Cow;
+use std::str::FromStr;
 
 /// All the "contexts" (sections) that Reaper keymaps can live in,
 /// with their exact numeric codes.
+///
+/// Codes without a variant (newer REAPER versions, extensions like SWS) are kept as
+/// [`ReaperActionSection::Custom`], so their entries still load and round-trip.
 #[derive(
-    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, IntoPrimitive, TryFromPrimitive,
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, IntoPrimitive, FromPrimitive,
 )]
 #[repr(u32)]
 pub enum ReaperActionSection {
@@ -31,12 +36,19 @@ pub enum ReaperActionSection {
     MidiEventList = 32061,
     MidiInline = 32062,
     MediaExplorer = 32063,
+    /// A code with no named variant. `From<u32>` never builds this for a named code.
+    #[num_enum(catch_all)]
+    Custom(u32),
 }
 
 impl ReaperActionSection {
-    /// Try to convert a raw `u32` into one of our `Section` variants.
+    /// Try to convert a raw `u32` into one of the named variants. Use `From<u32>` to
+    /// keep unknown codes as [`ReaperActionSection::Custom`].
     pub fn from_u32(n: u32) -> Option<Self> {
-        Self::try_from(n).ok()
+        match Self::from(n) {
+            ReaperActionSection::Custom(_) => None,
+            section => Some(section),
+        }
     }
 
     /// Convert a `Section` back into the raw `u32` code.
@@ -44,9 +56,48 @@ impl ReaperActionSection {
         self.into()
     }
 
-    /// Get the human-readable display name for comments
-    pub fn display_name(self) -> &'static str {
-        match self {
+    /// Resolve an alt-context number: 1–16 for `MainAlt1`..`MainAlt16`, 100 for the
+    /// alt recording section.
+    pub fn from_alt_index(index: usize) -> Option<Self> {
+        match index {
+            1..=16 | 100 => Self::from_u32(index as u32),
+            _ => None,
+        }
+    }
+
+    /// The alt-context number of `MainAlt1`..`MainAlt16`; see
+    /// [`ReaperActionSection::is_alt_recording`] for the alt recording section.
+    pub fn alt_index(self) -> Option<u8> {
+        match self.as_u32() {
+            n @ 1..=16 if self.is_main_context() => Some(n as u8),
+            _ => None,
+        }
+    }
+
+    pub fn is_alt_recording(self) -> bool {
+        self == ReaperActionSection::MainAltRecording
+    }
+
+    /// Main, its alt sections and the alt recording section.
+    pub fn is_main_context(self) -> bool {
+        !matches!(self, ReaperActionSection::Custom(_))
+            && matches!(self.as_u32(), 0..=16 | 100)
+    }
+
+    /// The MIDI editor, event list and inline editor.
+    pub fn is_midi_context(self) -> bool {
+        matches!(
+            self,
+            ReaperActionSection::MidiEditor
+                | ReaperActionSection::MidiEventList
+                | ReaperActionSection::MidiInline
+        )
+    }
+
+    /// Get the human-readable display name for comments, `"Section <code>"` for
+    /// unknown codes.
+    pub fn display_name(self) -> Cow<'static, str> {
+        let name = match self {
             ReaperActionSection::Main => "Main",
             ReaperActionSection::MainAltRecording => "Main (alt recording)",
             ReaperActionSection::MainAlt1 => "Main (alt-1)",
@@ -69,14 +120,64 @@ impl ReaperActionSection {
             ReaperActionSection::MidiEventList => "MIDI Event List", 
             ReaperActionSection::MidiInline => "MIDI Inline Editor",
             ReaperActionSection::MediaExplorer => "Media Explorer",
+            ReaperActionSection::Custom(code) => return Cow::Owned(format!("Section {}", code)),
+        };
+        Cow::Borrowed(name)
+    }
+
+    /// Reverse of [`ReaperActionSection::display_name`], ignoring case and surrounding
+    /// whitespace.
+    pub fn from_display_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        if let Some(code) = name
+            .split_once(' ')
+            .filter(|(prefix, _)| prefix.eq_ignore_ascii_case("Section"))
+            .and_then(|(_, code)| code.parse::<u32>().ok())
+        {
+            return Some(Self::from(code));
         }
+        [0, 100]
+            .into_iter()
+            .chain(1..=16)
+            .chain(32060..=32063)
+            .filter_map(Self::from_u32)
+            .find(|section| section.display_name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Parses a numeric code (`"32060"`, unknown ones as [`ReaperActionSection::Custom`]) or
+/// a display name (`"MIDI Editor"`), see [`ReaperActionSection::from_display_name`].
+impl FromStr for ReaperActionSection {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().parse::<u32>() {
+            Ok(code) => Ok(Self::from(code)),
+            Err(_) => Self::from_display_name(s)
+                .ok_or_else(|| ParseError::InvalidSectionName(s.to_string())),
+        }
+    }
+}
+
+/// Sections order by their numeric code, so `Main` and the alt sections come before
+/// the MIDI editor sections. A hand-built [`ReaperActionSection::Custom`] holding a named
+/// code isn't equal to the named variant, so it sorts right after it.
+impl PartialOrd for ReaperActionSection {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ReaperActionSection {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let key = |s: &Self| (s.as_u32(), matches!(s, ReaperActionSection::Custom(_)));
+        key(self).cmp(&key(other))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::ReaperActionSection;
-    use std::convert::TryFrom;
 
     #[test]
     fn round_trip_known_sections() {
@@ -103,12 +204,12 @@ mod tests {
                 expected
             );
 
-            // TryFrom primitive also works
-            let try_from = ReaperActionSection::try_from(raw).unwrap();
+            // From primitive also works
+            let from = ReaperActionSection::from(raw);
             assert_eq!(
-                try_from, expected,
-                "TryFrom::try_from({}) returned {:?}, expected {:?}",
-                raw, try_from, expected
+                from, expected,
+                "From::from({}) returned {:?}, expected {:?}",
+                raw, from, expected
             );
 
             // And back-conversion preserves the raw value
@@ -123,6 +224,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn alt_index_lookup() {
+        assert_eq!(ReaperActionSection::from_alt_index(1), Some(ReaperActionSection::MainAlt1));
+        assert_eq!(ReaperActionSection::from_alt_index(16), Some(ReaperActionSection::MainAlt16));
+        assert_eq!(
+            ReaperActionSection::from_alt_index(100),
+            Some(ReaperActionSection::MainAltRecording)
+        );
+        for bad in [0, 17, 99, 32060] {
+            assert_eq!(ReaperActionSection::from_alt_index(bad), None, "index {}", bad);
+        }
+    }
+
     #[test]
     fn invalid_section_codes() {
         // Some arbitrary values that aren't in the enum
@@ -132,12 +246,119 @@ mod tests {
                 "from_u32({}) should be None",
                 bad
             );
-            assert!(
-                ReaperActionSection::try_from(bad).is_err(),
-                "TryFrom::try_from({}) should Err",
+            assert_eq!(
+                ReaperActionSection::from(bad),
+                ReaperActionSection::Custom(bad),
+                "From::from({}) should keep the code",
                 bad
             );
+            assert_eq!(ReaperActionSection::Custom(bad).as_u32(), bad);
+        }
+    }
+
+    #[test]
+    fn context_predicates() {
+        use ReaperActionSection::*;
+        assert!(Main.is_main_context() && MainAlt1.is_main_context());
+        assert!(MainAlt16.is_main_context() && MainAltRecording.is_main_context());
+        assert!(MidiEditor.is_midi_context() && MidiEventList.is_midi_context());
+        assert!(MidiInline.is_midi_context());
+        for section in [MediaExplorer, Custom(17), Custom(99), Custom(32064), Custom(5)] {
+            assert!(!section.is_main_context() && !section.is_midi_context(), "{:?}", section);
+        }
+        assert!(!Main.is_midi_context() && !MidiEditor.is_main_context());
+
+        assert_eq!(Main.alt_index(), None);
+        assert_eq!(MainAlt1.alt_index(), Some(1));
+        assert_eq!(MainAlt16.alt_index(), Some(16));
+        assert_eq!(MainAltRecording.alt_index(), None);
+        assert_eq!(Custom(5).alt_index(), None);
+        assert!(MainAltRecording.is_alt_recording() && !MainAlt1.is_alt_recording());
+        for n in 1..=16u8 {
+            assert_eq!(ReaperActionSection::from_alt_index(n as usize).unwrap().alt_index(), Some(n));
+        }
+    }
+
+    #[test]
+    fn display_name_lookup() {
+        for code in [0, 100, 1, 4, 16, 32060, 32061, 32062, 32063, 32065] {
+            let section = ReaperActionSection::from(code);
+            assert_eq!(
+                ReaperActionSection::from_display_name(&section.display_name()),
+                Some(section)
+            );
+        }
+        assert_eq!(
+            ReaperActionSection::from_display_name("  midi editor "),
+            Some(ReaperActionSection::MidiEditor)
+        );
+        assert_eq!(
+            ReaperActionSection::from_display_name("MAIN (ALT-4)"),
+            Some(ReaperActionSection::MainAlt4)
+        );
+        assert_eq!(
+            ReaperActionSection::from_display_name("section 32060"),
+            Some(ReaperActionSection::MidiEditor)
+        );
+        for bad in ["", "Mai", "MIDI", "Main (alt-17)", "Section", "Section x"] {
+            assert_eq!(ReaperActionSection::from_display_name(bad), None, "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn parse_from_str() {
+        use crate::action_list::ParseError;
+
+        let cases = [
+            ("32060", ReaperActionSection::MidiEditor),
+            (" 0 ", ReaperActionSection::Main),
+            ("32065", ReaperActionSection::Custom(32065)),
+            ("MIDI Editor", ReaperActionSection::MidiEditor),
+            ("Main (alt-4)", ReaperActionSection::MainAlt4),
+            ("Section 32065", ReaperActionSection::Custom(32065)),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(text.parse::<ReaperActionSection>().unwrap(), expected, "{:?}", text);
+        }
+
+        for bad in ["", "-1", "4294967296", "MIDI Editr"] {
+            let err = bad.parse::<ReaperActionSection>().unwrap_err();
+            assert!(matches!(&err, ParseError::InvalidSectionName(name) if name == bad));
         }
+        assert_eq!(
+            "Midi Editr".parse::<ReaperActionSection>().unwrap_err().to_string(),
+            "invalid section name \"Midi Editr\""
+        );
+    }
+
+    #[test]
+    fn custom_section_code() {
+        let section = ReaperActionSection::from(32065);
+        assert_eq!(section, ReaperActionSection::Custom(32065));
+        assert_eq!(section.display_name(), "Section 32065");
+        assert_eq!(ReaperActionSection::MidiEditor.display_name(), "MIDI Editor");
+        assert_eq!(ReaperActionSection::from(32060), ReaperActionSection::MidiEditor);
+        assert!(section > ReaperActionSection::MediaExplorer);
+
+        let json = serde_json::to_string(&section).unwrap();
+        assert_eq!(serde_json::from_str::<ReaperActionSection>(&json).unwrap(), section);
+        let json = serde_json::to_string(&ReaperActionSection::Main).unwrap();
+        assert_eq!(json, "\"Main\"");
+    }
+
+    #[test]
+    fn ordering_agrees_with_equality() {
+        use std::collections::BTreeSet;
+        let custom = ReaperActionSection::Custom(32060);
+        assert_ne!(custom, ReaperActionSection::MidiEditor);
+        assert!(ReaperActionSection::MidiEditor < custom);
+        assert!(custom < ReaperActionSection::MidiEventList);
+        assert!(ReaperActionSection::Main < ReaperActionSection::MainAltRecording);
+
+        let set: BTreeSet<_> = [custom, ReaperActionSection::MidiEditor, custom].into();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&custom));
+        assert!(set.contains(&ReaperActionSection::MidiEditor));
     }
 
     #[test]
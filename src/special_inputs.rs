@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// Special input types that use modifier code 255 in Reaper keymap files
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum SpecialInput {
     /// Normal vertical mousewheel
     Mousewheel,
@@ -38,7 +38,8 @@ pub enum SpecialInput {
     /// Horizontal mousewheel with Ctrl+Alt+Shift modifiers
     CtrlAltShiftHorizWheel,
     
-    /// Multitouch zoom
+    /// Multitouch zoom. Pinching open and closed are the two directions of this one
+    /// input, as they are for a wheel; REAPER has no separate codes for them.
     MultiZoom,
     /// Multitouch zoom with Ctrl
     CtrlMultiZoom,
@@ -52,9 +53,10 @@ pub enum SpecialInput {
     /// Multitouch rotate with Ctrl
     CtrlMultiRotate,
     
-    /// Multitouch horizontal swipe
+    /// Multitouch horizontal swipe, left and right alike (the direction is in the value
+    /// the action receives, not in the key code)
     MultiHorz,
-    /// Multitouch vertical swipe
+    /// Multitouch vertical swipe, up and down alike
     MultiVert,
     
     /// Media keyboard keys (various values)
@@ -64,7 +66,133 @@ pub enum SpecialInput {
     Unknown(u16),
 }
 
+/// The family of a special input, without the modifiers baked into its variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SpecialInputBase {
+    Mousewheel,
+    HorizWheel,
+    MultiZoom,
+    MultiRotate,
+    MultiHorz,
+    MultiVert,
+    MediaKey,
+    Unknown,
+}
+
 impl SpecialInput {
+    const STANDARD: [SpecialInput; 24] = [
+        // Mousewheel
+        SpecialInput::Mousewheel,
+        SpecialInput::CtrlMousewheel,
+        SpecialInput::AltMousewheel,
+        SpecialInput::CtrlAltMousewheel,
+        SpecialInput::ShiftMousewheel,
+        SpecialInput::CtrlShiftMousewheel,
+        SpecialInput::AltShiftMousewheel,
+        SpecialInput::CtrlAltShiftMousewheel,
+        // Horizontal wheel
+        SpecialInput::HorizWheel,
+        SpecialInput::AltHorizWheel,
+        SpecialInput::CtrlHorizWheel,
+        SpecialInput::CtrlAltHorizWheel,
+        SpecialInput::ShiftHorizWheel,
+        SpecialInput::CtrlShiftHorizWheel,
+        SpecialInput::AltShiftHorizWheel,
+        SpecialInput::CtrlAltShiftHorizWheel,
+        // Multitouch
+        SpecialInput::MultiZoom,
+        SpecialInput::CtrlMultiZoom,
+        SpecialInput::AltMultiZoom,
+        SpecialInput::CtrlAltShiftMultiZoom,
+        SpecialInput::MultiRotate,
+        SpecialInput::CtrlMultiRotate,
+        SpecialInput::MultiHorz,
+        SpecialInput::MultiVert,
+    ];
+
+    /// Every variant except [`SpecialInput::MediaKey`] and [`SpecialInput::Unknown`]:
+    /// the mousewheel family, then the horizontal wheel, then multitouch gestures.
+    pub fn all_standard_variants() -> &'static [SpecialInput] {
+        &Self::STANDARD
+    }
+
+    /// Reverse of `Display`: "Mousewheel", "Ctrl+HorizWheel", "MediaKey(744)" and so on,
+    /// ignoring case and surrounding whitespace.
+    pub fn from_display_name(s: &str) -> Option<SpecialInput> {
+        let s = s.trim();
+        if let Some(input) = Self::all_standard_variants()
+            .iter()
+            .find(|input| input.to_string().eq_ignore_ascii_case(s))
+        {
+            return Some(*input);
+        }
+        let (name, code) = s.strip_suffix(')')?.split_once('(')?;
+        let code = code.parse().ok()?;
+        if name.eq_ignore_ascii_case("MediaKey") {
+            Some(SpecialInput::MediaKey(code))
+        } else if name.eq_ignore_ascii_case("Unknown") {
+            Some(SpecialInput::Unknown(code))
+        } else {
+            None
+        }
+    }
+
+    /// The family this input belongs to (e.g. `AltShiftHorizWheel` is a `HorizWheel`).
+    pub fn base(self) -> SpecialInputBase {
+        match self {
+            SpecialInput::Mousewheel
+            | SpecialInput::CtrlMousewheel
+            | SpecialInput::AltMousewheel
+            | SpecialInput::CtrlAltMousewheel
+            | SpecialInput::ShiftMousewheel
+            | SpecialInput::CtrlShiftMousewheel
+            | SpecialInput::AltShiftMousewheel
+            | SpecialInput::CtrlAltShiftMousewheel => SpecialInputBase::Mousewheel,
+
+            SpecialInput::HorizWheel
+            | SpecialInput::AltHorizWheel
+            | SpecialInput::CtrlHorizWheel
+            | SpecialInput::CtrlAltHorizWheel
+            | SpecialInput::ShiftHorizWheel
+            | SpecialInput::CtrlShiftHorizWheel
+            | SpecialInput::AltShiftHorizWheel
+            | SpecialInput::CtrlAltShiftHorizWheel => SpecialInputBase::HorizWheel,
+
+            SpecialInput::MultiZoom
+            | SpecialInput::CtrlMultiZoom
+            | SpecialInput::AltMultiZoom
+            | SpecialInput::CtrlAltShiftMultiZoom => SpecialInputBase::MultiZoom,
+
+            SpecialInput::MultiRotate | SpecialInput::CtrlMultiRotate => {
+                SpecialInputBase::MultiRotate
+            }
+
+            SpecialInput::MultiHorz => SpecialInputBase::MultiHorz,
+            SpecialInput::MultiVert => SpecialInputBase::MultiVert,
+            SpecialInput::MediaKey(_) => SpecialInputBase::MediaKey,
+            SpecialInput::Unknown(_) => SpecialInputBase::Unknown,
+        }
+    }
+
+    /// The modifiers baked into the variant, as REAPER encodes them in the low bits of the
+    /// key code: 0x01 Ctrl, 0x02 Alt, 0x04 Shift. Always 0 for media keys and unknown
+    /// inputs.
+    pub fn modifier_bits(self) -> u8 {
+        match self {
+            SpecialInput::MediaKey(_) | SpecialInput::Unknown(_) => 0,
+            _ => (self.to_key_code() & 0x07) as u8,
+        }
+    }
+
+    /// The same input without modifiers (e.g. `AltShiftHorizWheel` is `HorizWheel`).
+    /// Media keys and unknown inputs are returned as they are.
+    pub fn base_input(self) -> SpecialInput {
+        match self {
+            SpecialInput::MediaKey(_) | SpecialInput::Unknown(_) => self,
+            _ => SpecialInput::from_key_code(self.to_key_code() & !0x07),
+        }
+    }
+
     /// Convert a key code (used with modifier 255) to a SpecialInput
     pub fn from_key_code(key_code: u16) -> Self {
         match key_code {
@@ -208,15 +336,94 @@ mod tests {
     }
     
     #[test]
-    fn test_round_trip() {
-        let inputs = vec![
-            SpecialInput::Mousewheel,
-            SpecialInput::AltHorizWheel,
-            SpecialInput::CtrlMultiZoom,
-            SpecialInput::MultiVert,
+    fn test_base() {
+        assert_eq!(SpecialInput::Mousewheel.base(), SpecialInputBase::Mousewheel);
+        assert_eq!(SpecialInput::CtrlAltShiftMousewheel.base(), SpecialInputBase::Mousewheel);
+        assert_eq!(SpecialInput::AltShiftHorizWheel.base(), SpecialInputBase::HorizWheel);
+        assert_eq!(SpecialInput::CtrlMultiZoom.base(), SpecialInputBase::MultiZoom);
+        assert_eq!(SpecialInput::MediaKey(232).base(), SpecialInputBase::MediaKey);
+        assert_eq!(SpecialInput::Unknown(7).base(), SpecialInputBase::Unknown);
+    }
+
+    #[test]
+    fn test_modifier_bits_and_base_input() {
+        let cases = [
+            (SpecialInput::Mousewheel, 0x00, SpecialInput::Mousewheel),
+            (SpecialInput::CtrlMousewheel, 0x01, SpecialInput::Mousewheel),
+            (SpecialInput::AltMousewheel, 0x02, SpecialInput::Mousewheel),
+            (SpecialInput::CtrlAltShiftMousewheel, 0x07, SpecialInput::Mousewheel),
+            (SpecialInput::ShiftHorizWheel, 0x04, SpecialInput::HorizWheel),
+            (SpecialInput::AltShiftHorizWheel, 0x06, SpecialInput::HorizWheel),
+            (SpecialInput::CtrlAltShiftMultiZoom, 0x07, SpecialInput::MultiZoom),
+            (SpecialInput::CtrlMultiRotate, 0x01, SpecialInput::MultiRotate),
+            (SpecialInput::MultiHorz, 0x00, SpecialInput::MultiHorz),
+            (SpecialInput::MultiVert, 0x00, SpecialInput::MultiVert),
+            (SpecialInput::MediaKey(745), 0x00, SpecialInput::MediaKey(745)),
+            (SpecialInput::Unknown(7), 0x00, SpecialInput::Unknown(7)),
         ];
-        
+        for (input, bits, base) in cases {
+            assert_eq!(input.modifier_bits(), bits, "{}", input);
+            assert_eq!(input.base_input(), base, "{}", input);
+            assert_eq!(input.base_input().base(), input.base());
+        }
+    }
+
+    #[test]
+    fn test_from_display_name() {
+        let mut inputs = SpecialInput::all_standard_variants().to_vec();
+        inputs.extend([SpecialInput::MediaKey(744), SpecialInput::Unknown(7)]);
         for input in inputs {
+            assert_eq!(SpecialInput::from_display_name(&input.to_string()), Some(input));
+        }
+        assert_eq!(
+            SpecialInput::from_display_name(" shift+horizwheel "),
+            Some(SpecialInput::ShiftHorizWheel)
+        );
+        for name in ["", "Wheel", "Shift+Ctrl+Mousewheel", "MediaKey(x)", "Key(65)"] {
+            assert_eq!(SpecialInput::from_display_name(name), None, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_unnamed_multitouch_codes_round_trip() {
+        // Swipes with modifiers have no variant of their own yet, but keep their code
+        for code in (169..=175).chain(185..=191) {
+            let input = SpecialInput::from_key_code(code);
+            assert_eq!(input, SpecialInput::Unknown(code));
+            assert_eq!(input.to_key_code(), code);
+            assert_eq!(SpecialInput::from_display_name(&input.to_string()), Some(input));
+        }
+    }
+
+    #[test]
+    fn test_all_standard_variants() {
+        let all = SpecialInput::all_standard_variants();
+        assert_eq!(all.len(), 24);
+        for (i, input) in all.iter().enumerate() {
+            assert!(!matches!(input, SpecialInput::MediaKey(_) | SpecialInput::Unknown(_)));
+            assert!(!all[..i].contains(input), "{} listed twice", input);
+        }
+
+        // Grouped by family, in that order
+        let families: Vec<SpecialInputBase> = all.iter().map(|input| input.base()).collect();
+        let mut grouped = families.clone();
+        grouped.dedup();
+        assert_eq!(
+            grouped,
+            [
+                SpecialInputBase::Mousewheel,
+                SpecialInputBase::HorizWheel,
+                SpecialInputBase::MultiZoom,
+                SpecialInputBase::MultiRotate,
+                SpecialInputBase::MultiHorz,
+                SpecialInputBase::MultiVert,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for &input in SpecialInput::all_standard_variants() {
             let key_code = input.to_key_code();
             let parsed = SpecialInput::from_key_code(key_code);
             assert_eq!(input, parsed);
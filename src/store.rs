@@ -0,0 +1,314 @@
+//! A keymap shared between threads, with change notifications.
+
+use crate::action_list::{KeymapDiff, ReaperActionList};
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Sent to subscribers after every committed write.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeymapChange {
+    /// Generation the write produced.
+    pub generation: u64,
+    /// Entries the write added, removed or changed, matched like
+    /// [`ReaperActionList::diff`].
+    pub diff: KeymapDiff,
+}
+
+#[derive(Debug, Default)]
+struct Versioned {
+    generation: u64,
+    /// Shared with the write guard's snapshot, so the list is only copied when a write
+    /// actually changes it.
+    list: Arc<ReaperActionList>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    state: RwLock<Versioned>,
+    subscribers: Mutex<Vec<Sender<KeymapChange>>>,
+}
+
+/// A [`ReaperActionList`] behind an `Arc<RwLock<_>>`, cheap to clone and share between
+/// threads.
+///
+/// Every write bumps a generation counter, which readers see together with the list it
+/// belongs to, and notifies subscribers once the write lock is released, in generation
+/// order. A panic while holding a guard doesn't poison the store; the next access sees
+/// whatever was written.
+#[derive(Debug, Clone, Default)]
+pub struct KeymapStore {
+    shared: Arc<Shared>,
+}
+
+/// Shared access to the keymap, see [`KeymapStore::read`].
+pub struct KeymapReadGuard<'a> {
+    guard: RwLockReadGuard<'a, Versioned>,
+}
+
+/// Exclusive access to the keymap, see [`KeymapStore::write`]. The write is committed,
+/// and subscribers notified, when the guard is dropped.
+pub struct KeymapWriteGuard<'a> {
+    store: &'a KeymapStore,
+    guard: Option<RwLockWriteGuard<'a, Versioned>>,
+    before: Arc<ReaperActionList>,
+}
+
+impl KeymapStore {
+    /// A store holding `list` at generation 0.
+    pub fn new(list: ReaperActionList) -> Self {
+        KeymapStore {
+            shared: Arc::new(Shared {
+                state: RwLock::new(Versioned {
+                    generation: 0,
+                    list: Arc::new(list),
+                }),
+                subscribers: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    pub fn read(&self) -> KeymapReadGuard<'_> {
+        KeymapReadGuard {
+            guard: self.shared.state.read().unwrap_or_else(PoisonError::into_inner),
+        }
+    }
+
+    /// Lock the keymap for writing. Dropping the guard commits the write, even if
+    /// nothing was changed.
+    pub fn write(&self) -> KeymapWriteGuard<'_> {
+        let guard = self
+            .shared
+            .state
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        KeymapWriteGuard {
+            store: self,
+            before: Arc::clone(&guard.list),
+            guard: Some(guard),
+        }
+    }
+
+    /// The current generation; 0 until the first write.
+    pub fn generation(&self) -> u64 {
+        self.read().generation()
+    }
+
+    /// Receive a [`KeymapChange`] for every write committed from now on.
+    pub fn subscribe(&self) -> Receiver<KeymapChange> {
+        let (sender, receiver) = mpsc::channel();
+        self.lock_subscribers().push(sender);
+        receiver
+    }
+
+    /// Load `path` and swap it in as a single write. Parsing happens before taking the
+    /// lock, so readers are never blocked on file I/O. Returns the new generation.
+    pub fn replace_from_file<P: AsRef<Path>>(&self, path: P) -> io::Result<u64> {
+        let list = ReaperActionList::load_from_file(path)?;
+        let mut guard = self.write();
+        guard.state_mut().list = Arc::new(list);
+        let generation = guard.generation() + 1;
+        drop(guard);
+        Ok(generation)
+    }
+
+    fn lock_subscribers(&self) -> std::sync::MutexGuard<'_, Vec<Sender<KeymapChange>>> {
+        self.shared
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl KeymapReadGuard<'_> {
+    /// Generation of the keymap this guard sees.
+    pub fn generation(&self) -> u64 {
+        self.guard.generation
+    }
+}
+
+impl Deref for KeymapReadGuard<'_> {
+    type Target = ReaperActionList;
+
+    fn deref(&self) -> &ReaperActionList {
+        &self.guard.list
+    }
+}
+
+impl KeymapWriteGuard<'_> {
+    /// Generation before this write is committed.
+    pub fn generation(&self) -> u64 {
+        self.state().generation
+    }
+
+    fn state(&self) -> &Versioned {
+        self.guard.as_ref().expect("guard is only taken on drop")
+    }
+
+    fn state_mut(&mut self) -> &mut Versioned {
+        self.guard.as_mut().expect("guard is only taken on drop")
+    }
+}
+
+impl Deref for KeymapWriteGuard<'_> {
+    type Target = ReaperActionList;
+
+    fn deref(&self) -> &ReaperActionList {
+        &self.state().list
+    }
+}
+
+impl DerefMut for KeymapWriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut ReaperActionList {
+        Arc::make_mut(&mut self.state_mut().list)
+    }
+}
+
+impl Drop for KeymapWriteGuard<'_> {
+    fn drop(&mut self) {
+        let Some(mut guard) = self.guard.take() else {
+            return;
+        };
+        guard.generation += 1;
+        let generation = guard.generation;
+        let after = Arc::clone(&guard.list);
+        // The next writer has to wait for the subscribers before it can notify them, so
+        // changes arrive in generation order. The diff is computed outside the write
+        // lock, so readers aren't held up by it
+        let mut subscribers = self.store.lock_subscribers();
+        drop(guard);
+        if subscribers.is_empty() {
+            return;
+        }
+        let diff = if Arc::ptr_eq(&self.before, &after) {
+            KeymapDiff::default()
+        } else {
+            self.before.diff(&after)
+        };
+        let change = KeymapChange { generation, diff };
+        subscribers.retain(|sender| sender.send(change.clone()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action_list::make_test_action_list;
+    use std::thread;
+
+    #[test]
+    fn test_store_readers_see_consistent_generations() {
+        const WRITES: u64 = 50;
+        let store = KeymapStore::default();
+        let changes = store.subscribe();
+        let template = make_test_action_list().entries[0].clone();
+
+        // Every write leaves exactly `generation` entries, so a reader can tell whether
+        // the list and the generation it sees belong together
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let store = store.clone();
+                thread::spawn(move || {
+                    let mut last = 0;
+                    while last < WRITES {
+                        let guard = store.read();
+                        assert_eq!(guard.entries.len() as u64, guard.generation());
+                        assert!(guard.generation() >= last);
+                        last = guard.generation();
+                    }
+                })
+            })
+            .collect();
+
+        let writer = {
+            let store = store.clone();
+            let template = template.clone();
+            thread::spawn(move || {
+                for _ in 0..WRITES {
+                    store.write().entries.push(template.clone());
+                }
+            })
+        };
+
+        writer.join().unwrap();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        let received: Vec<KeymapChange> = changes.try_iter().collect();
+        assert_eq!(received.len() as u64, WRITES);
+        for (i, change) in received.iter().enumerate() {
+            assert_eq!(change.generation, i as u64 + 1);
+            assert_eq!(change.diff.added, vec![template.clone()]);
+        }
+        assert_eq!(store.generation(), WRITES);
+    }
+
+    #[test]
+    fn test_store_changes_arrive_in_generation_order() {
+        const WRITERS: u64 = 4;
+        const WRITES: u64 = 25;
+        let store = KeymapStore::new(make_test_action_list());
+        let changes = store.subscribe();
+        let template = make_test_action_list().entries[0].clone();
+
+        // Every write inserts at the front, which shifts every entry after it
+        let writers: Vec<_> = (0..WRITERS)
+            .map(|_| {
+                let store = store.clone();
+                let template = template.clone();
+                thread::spawn(move || {
+                    for _ in 0..WRITES {
+                        store.write().entries.insert(0, template.clone());
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let received: Vec<KeymapChange> = changes.try_iter().collect();
+        let generations: Vec<u64> = received.iter().map(|c| c.generation).collect();
+        assert_eq!(generations, (1..=WRITERS * WRITES).collect::<Vec<_>>());
+        for change in &received {
+            assert_eq!(change.diff.added, vec![template.clone()]);
+            assert!(change.diff.removed.is_empty());
+            assert!(change.diff.modified.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_store_replace_from_file() {
+        let store = KeymapStore::new(make_test_action_list());
+        let changes = store.subscribe();
+
+        let generation = store
+            .replace_from_file("resources/test-file.reaperkeymap")
+            .unwrap();
+        assert_eq!(generation, 1);
+        assert_eq!(
+            *store.read(),
+            ReaperActionList::load_from_file("resources/test-file.reaperkeymap").unwrap()
+        );
+
+        // A failed load leaves the store alone
+        assert!(store.replace_from_file("resources/does-not-exist").is_err());
+        assert_eq!(store.generation(), 1);
+
+        // Untouched writes still count, with an empty diff
+        drop(store.write());
+        let received: Vec<KeymapChange> = changes.try_iter().collect();
+        assert_eq!(received.len(), 2);
+        assert!(!received[0].diff.is_empty());
+        assert_eq!(received[1].generation, 2);
+        assert!(received[1].diff.is_empty());
+
+        // Dropped receivers are forgotten
+        drop(changes);
+        drop(store.write());
+        assert!(store.shared.subscribers.lock().unwrap().is_empty());
+    }
+}
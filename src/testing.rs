@@ -0,0 +1,432 @@
+//! Realistic keymaps for tests, behind the `test-util` feature.
+
+use crate::action_list::{
+    ActionEntry, ActionFlags, Comment, KeyEntry, KeyInputType, ReaperActionList, ReaperEntry,
+    ScriptEntry, TerminationBehavior, analyze_description,
+};
+use crate::keycodes::KeyCode;
+use crate::modifiers::Modifiers;
+use crate::sections::ReaperActionSection;
+use crate::special_inputs::SpecialInput;
+
+/// Native main-section actions generated bindings run, with their REAPER names.
+const ACTIONS: &[(&str, &str)] = &[
+    ("40044", "Transport: Play/stop"),
+    ("1013", "Transport: Record"),
+    ("40001", "Track: Insert new track"),
+    ("40005", "Track: Remove tracks"),
+    ("6", "Track: Toggle mute for selected tracks"),
+    ("7", "Track: Toggle solo for selected tracks"),
+    ("40026", "File: Save project"),
+    ("40022", "File: Save project as..."),
+    ("40029", "Edit: Undo"),
+    ("40030", "Edit: Redo"),
+    ("40057", "Edit: Copy items/tracks/envelope points (depending on focus) ignoring time selection"),
+    ("40058", "Item: Paste items/tracks"),
+    ("40757", "Item: Split items at edit cursor (no change selection)"),
+    ("40153", "Item: Open in built-in MIDI editor (set default behavior in preferences)"),
+    ("40042", "Transport: Go to start of project"),
+    ("40043", "Transport: Go to end of project"),
+    ("40157", "Markers: Insert marker at current position"),
+    ("40296", "Track: Select all tracks"),
+    ("40182", "Item: Select all items"),
+    ("40295", "View: Zoom out project"),
+];
+
+/// Native actions of the MIDI editor, event list and inline editor.
+const MIDI_ACTIONS: &[(&str, &str)] = &[
+    ("40010", "Edit: Copy"),
+    ("40011", "Edit: Paste"),
+    ("40012", "Edit: Cut"),
+    ("40013", "Edit: Undo"),
+    ("40014", "Edit: Redo"),
+    ("40006", "Edit: Select all events"),
+    ("40667", "Edit: Delete events"),
+    ("40177", "Edit: Move notes up one semitone"),
+    ("40178", "Edit: Move notes down one semitone"),
+    ("40179", "Edit: Move notes up one octave"),
+    ("40180", "Edit: Move notes down one octave"),
+    ("40111", "View: Zoom in horizontally"),
+    ("40112", "View: Zoom out horizontally"),
+    ("1014", "View: Toggle snap to grid"),
+];
+
+/// Native actions of the media explorer.
+const MEDIA_EXPLORER_ACTIONS: &[(&str, &str)] = &[
+    ("1008", "Preview: Play"),
+    ("1009", "Preview: Stop"),
+    ("1010", "Preview: Play/stop"),
+    ("1011", "Preview: Pause"),
+    ("1002", "Browser: Go to parent folder"),
+    ("1003", "Browser: Refresh"),
+    ("40018", "Insert media on new track"),
+    ("40019", "Insert media into project"),
+];
+
+/// The native actions generated bindings in `section` run. Sections without a table of
+/// their own (unknown codes) borrow the main section's.
+fn native_actions(section: ReaperActionSection) -> &'static [(&'static str, &'static str)] {
+    if section.is_midi_context() {
+        MIDI_ACTIONS
+    } else if section == ReaperActionSection::MediaExplorer {
+        MEDIA_EXPLORER_ACTIONS
+    } else {
+        ACTIONS
+    }
+}
+
+/// Mousewheel actions of the main section.
+const SPECIAL_INPUTS: &[(SpecialInput, &str, &str)] = &[
+    (
+        SpecialInput::Mousewheel,
+        "989",
+        "View: Scroll vertically (MIDI CC relative/mousewheel)",
+    ),
+    (
+        SpecialInput::HorizWheel,
+        "974",
+        "View: Scroll horizontally (MIDI CC relative/mousewheel)",
+    ),
+    (
+        SpecialInput::CtrlMousewheel,
+        "990",
+        "View: Zoom horizontally (MIDI CC relative/mousewheel)",
+    ),
+];
+
+/// Mousewheel actions of the MIDI sections.
+const MIDI_SPECIAL_INPUTS: &[(SpecialInput, &str, &str)] = &[
+    (
+        SpecialInput::Mousewheel,
+        "40432",
+        "View: Scroll vertically (MIDI relative/mousewheel)",
+    ),
+    (
+        SpecialInput::HorizWheel,
+        "40660",
+        "View: Scroll horizontally (MIDI relative/mousewheel)",
+    ),
+    (
+        SpecialInput::CtrlMousewheel,
+        "40431",
+        "View: Zoom horizontally (MIDI relative/mousewheel)",
+    ),
+];
+
+/// The mousewheel bindings generated in `section`; none in the media explorer.
+fn special_input_actions(
+    section: ReaperActionSection,
+) -> &'static [(SpecialInput, &'static str, &'static str)] {
+    if section.is_midi_context() {
+        MIDI_SPECIAL_INPUTS
+    } else if section == ReaperActionSection::MediaExplorer {
+        &[]
+    } else {
+        SPECIAL_INPUTS
+    }
+}
+
+/// Deterministic SplitMix64, so generated keymaps only depend on the seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn hex(&mut self, digits: usize) -> String {
+        (0..digits)
+            .map(|_| char::from_digit(self.below(16) as u32, 16).unwrap())
+            .collect()
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+/// Builds deterministic keymaps with valid, conflict-free bindings and REAPER-style
+/// comments.
+///
+/// ```ignore
+/// let list = KeymapGenerator::new()
+///     .sections(&[ReaperActionSection::Main, ReaperActionSection::MidiEditor])
+///     .keys_per_section(50)
+///     .include_scripts(3)
+///     .seed(7)
+///     .build();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeymapGenerator {
+    sections: Vec<ReaperActionSection>,
+    keys_per_section: usize,
+    scripts: usize,
+    custom_actions: usize,
+    special_inputs: bool,
+    seed: u64,
+}
+
+impl Default for KeymapGenerator {
+    fn default() -> Self {
+        KeymapGenerator {
+            sections: vec![ReaperActionSection::Main],
+            keys_per_section: 20,
+            scripts: 0,
+            custom_actions: 0,
+            special_inputs: false,
+            seed: 0,
+        }
+    }
+}
+
+impl KeymapGenerator {
+    /// 20 bindings in the main section, nothing else, seed 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sections to generate bindings in; an empty slice keeps just `Main`.
+    pub fn sections(mut self, sections: &[ReaperActionSection]) -> Self {
+        self.sections = if sections.is_empty() {
+            vec![ReaperActionSection::Main]
+        } else {
+            sections.to_vec()
+        };
+        self
+    }
+
+    /// Keyboard bindings per section, capped at the number of distinct combos of
+    /// letters, digits and F1–F12 with any modifiers.
+    pub fn keys_per_section(mut self, n: usize) -> Self {
+        self.keys_per_section = n;
+        self
+    }
+
+    /// SCR entries, spread over the sections, each bound to a key of its own.
+    pub fn include_scripts(mut self, n: usize) -> Self {
+        self.scripts = n;
+        self
+    }
+
+    /// ACT entries, spread over the sections, each bound to a key of its own.
+    pub fn include_custom_actions(mut self, n: usize) -> Self {
+        self.custom_actions = n;
+        self
+    }
+
+    /// Mousewheel bindings (vertical, horizontal, Ctrl) in every section except the
+    /// media explorer.
+    pub fn include_special_inputs(mut self, include: bool) -> Self {
+        self.special_inputs = include;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn build(&self) -> ReaperActionList {
+        let mut rng = Rng(self.seed);
+        let mut entries = Vec::new();
+        let mut bindings = Vec::new();
+
+        // Definitions first, as REAPER writes them; their bindings come out of the
+        // same pool of free combos as the rest
+        let mut definitions = Vec::new();
+        for i in 0..self.scripts {
+            let section = self.sections[i % self.sections.len()];
+            let command_id = format!("RS{}", rng.hex(40));
+            let name = format!("Generated script {}.lua", i + 1);
+            entries.push(ReaperEntry::Script(ScriptEntry {
+                termination_behavior: TerminationBehavior::Prompt,
+                section,
+                command_id: command_id.clone(),
+                description: format!("Custom: {}", name),
+                path: format!("Generated/{}", name),
+            }));
+            definitions.push((section, format!("_{}", command_id), format!("Script: {}", name)));
+        }
+        for i in 0..self.custom_actions {
+            let section = self.sections[i % self.sections.len()];
+            let command_id = rng.hex(32);
+            let description = format!("Custom: Generated action {}", i + 1);
+            let steps = 1 + rng.below(3);
+            let native = native_actions(section);
+            let action_ids = (0..steps)
+                .map(|_| native[rng.below(native.len())].0.to_string())
+                .collect();
+            entries.push(ReaperEntry::Action(ActionEntry {
+                action_flags: ActionFlags::empty(),
+                section,
+                command_id: command_id.clone(),
+                description: description.clone(),
+                action_ids,
+                quoted_ids: Vec::new(),
+            }));
+            definitions.push((section, format!("_{}", command_id), description));
+        }
+
+        let keys: Vec<KeyCode> = (KeyCode::A.as_u16()..=KeyCode::Z.as_u16())
+            .chain(KeyCode::Key0.as_u16()..=KeyCode::Key9.as_u16())
+            .chain(KeyCode::F1.as_u16()..=KeyCode::F12.as_u16())
+            .filter_map(KeyCode::from_u16)
+            .collect();
+        let modifier_sets: Vec<Modifiers> = (0..16u16)
+            .filter_map(|n| Modifiers::from_bits(n << 2))
+            .collect();
+
+        for &section in &self.sections {
+            let mut combos: Vec<(Modifiers, KeyCode)> = modifier_sets
+                .iter()
+                .flat_map(|m| keys.iter().map(move |k| (*m, *k)))
+                .collect();
+            rng.shuffle(&mut combos);
+
+            let defined = definitions
+                .iter()
+                .filter(|(s, _, _)| *s == section)
+                .map(|(_, id, name)| (id.clone(), name.clone()));
+            let actions = native_actions(section);
+            let native = (0..self.keys_per_section).map(|_| {
+                let (id, name) = actions[rng.below(actions.len())];
+                (id.to_string(), name.to_string())
+            });
+            let commands: Vec<(String, String)> = defined.chain(native).collect();
+            for ((modifiers, key), (command_id, description)) in combos.into_iter().zip(commands) {
+                bindings.push(key_entry(
+                    section,
+                    modifiers,
+                    KeyInputType::Regular(key),
+                    command_id,
+                    description,
+                ));
+            }
+
+            if self.special_inputs {
+                for &(input, command_id, description) in special_input_actions(section) {
+                    bindings.push(key_entry(
+                        section,
+                        Modifiers::SPECIAL_INPUT,
+                        KeyInputType::Special(input),
+                        command_id.to_string(),
+                        description.to_string(),
+                    ));
+                }
+            }
+        }
+
+        entries.extend(bindings.into_iter().map(ReaperEntry::Key));
+        ReaperActionList::from(entries)
+    }
+}
+
+fn key_entry(
+    section: ReaperActionSection,
+    modifiers: Modifiers,
+    key_input: KeyInputType,
+    command_id: String,
+    description: String,
+) -> KeyEntry {
+    let mut entry = KeyEntry {
+        modifiers,
+        key_input,
+        command_id,
+        section,
+        comment: None,
+        trailing: None,
+    };
+    let mut comment = Comment::from_key_entry(&entry);
+    (comment.parsed_action_name, comment.is_midi_relative) =
+        analyze_description(Some(&description));
+    comment.action_description = Some(description);
+    entry.comment = Some(comment);
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generator() -> KeymapGenerator {
+        KeymapGenerator::new()
+            .sections(&[ReaperActionSection::Main, ReaperActionSection::MidiEditor])
+            .keys_per_section(120)
+            .include_scripts(4)
+            .include_custom_actions(3)
+            .include_special_inputs(true)
+            .seed(42)
+    }
+
+    #[test]
+    fn test_generator_is_deterministic_and_valid() {
+        let list = generator().build();
+        assert_eq!(list, generator().build());
+        assert_ne!(list, generator().seed(43).build());
+        assert_eq!(list.validate(), Vec::new());
+
+        let counts = list.counts();
+        assert_eq!(counts.scripts, 4);
+        assert_eq!(counts.actions, 3);
+        assert_eq!(
+            counts.keys,
+            2 * 120 + 4 + 3 + SPECIAL_INPUTS.len() + MIDI_SPECIAL_INPUTS.len()
+        );
+
+        // Every script and custom action is bound, and the list survives a text round-trip
+        for entry in list.entries.iter().filter(|e| !matches!(e, ReaperEntry::Key(_))) {
+            let id = match entry {
+                ReaperEntry::Script(s) => format!("_{}", s.command_id),
+                ReaperEntry::Action(a) => format!("_{}", a.command_id),
+                ReaperEntry::Key(_) | ReaperEntry::Raw(_) => unreachable!(),
+            };
+            assert!(list.keys().iter().any(|k| k.command_id == id), "{} unbound", id);
+        }
+        let lines: Vec<String> = list.entries.iter().map(ReaperEntry::to_line).collect();
+        assert_eq!(ReaperActionList::from_lines(&lines).unwrap(), list);
+
+        // Native bindings run actions of their own section
+        for key in list.keys().iter().filter(|k| !k.command_id.starts_with('_')) {
+            let actions = native_actions(key.section);
+            let specials = special_input_actions(key.section);
+            assert!(
+                actions.iter().any(|(id, _)| *id == key.command_id)
+                    || specials.iter().any(|(_, id, _)| *id == key.command_id),
+                "{:?} runs {}",
+                key.section,
+                key.command_id
+            );
+        }
+        let midi_names: Vec<_> = list
+            .keys()
+            .into_iter()
+            .filter(|k| k.section == ReaperActionSection::MidiEditor)
+            .filter_map(|k| k.comment?.action_description)
+            .collect();
+        assert!(!midi_names.iter().any(|n| n == "Track: Insert new track"));
+
+        // No sections means just Main
+        let main_only = KeymapGenerator::new()
+            .sections(&[])
+            .include_scripts(2)
+            .include_custom_actions(2)
+            .build();
+        assert_eq!(main_only.sections(), [ReaperActionSection::Main].into());
+        assert_eq!(main_only.counts().scripts, 2);
+
+        // Asking for more keys than there are combos stops at the combos
+        let full = KeymapGenerator::new().keys_per_section(10_000).build();
+        assert_eq!(full.counts().keys, 48 * 16);
+        assert_eq!(full.validate(), Vec::new());
+    }
+}
@@ -1,6 +1,7 @@
 use rs_keymap_parser::action_list::{ReaperActionList, ReaperEntry, KeyEntry, KeyInputType, Comment};
-use rs_keymap_parser::special_inputs::SpecialInput;
+use rs_keymap_parser::special_inputs::{SpecialInput, SpecialInputBase};
 use rs_keymap_parser::sections::ReaperActionSection;
+use rs_keymap_parser::testing::KeymapGenerator;
 use std::fs;
 
 #[test]
@@ -12,7 +13,7 @@ fn test_large_integration_with_scr_and_act_entries() {
     let action_list = ReaperActionList::load_from_file(original_path)
         .expect("Failed to load large keymap file");
     
-    println!("✅ Successfully parsed {} entries from large keymap file", action_list.0.len());
+    println!("✅ Successfully parsed {} entries from large keymap file", action_list.entries.len());
     
     // Step 2: Create output directory in target
     let output_dir = std::path::Path::new("target/generated");
@@ -43,12 +44,12 @@ fn test_large_integration_with_scr_and_act_entries() {
     
     // Step 6: Compare entry counts
     println!("📊 Comparing large keymap results:");
-    println!("   Original entries: {}", action_list.0.len());
-    println!("   Reparsed entries: {}", reparsed_list.0.len());
+    println!("   Original entries: {}", action_list.entries.len());
+    println!("   Reparsed entries: {}", reparsed_list.entries.len());
     
     assert_eq!(
-        action_list.0.len(), 
-        reparsed_list.0.len(),
+        action_list.entries.len(), 
+        reparsed_list.entries.len(),
         "Entry count mismatch after round-trip"
     );
     
@@ -56,7 +57,7 @@ fn test_large_integration_with_scr_and_act_entries() {
     let mut matches = 0;
     let mut mismatches = 0;
     
-    for (i, (original, reparsed)) in action_list.0.iter().zip(reparsed_list.0.iter()).enumerate() {
+    for (i, (original, reparsed)) in action_list.entries.iter().zip(reparsed_list.entries.iter()).enumerate() {
         if original == reparsed {
             matches += 1;
         } else {
@@ -71,27 +72,18 @@ fn test_large_integration_with_scr_and_act_entries() {
     println!("   ⚠️  Mismatches: {}", mismatches);
     
     // Step 8: Analyze entry types
-    let mut key_count = 0;
-    let mut scr_count = 0;
-    let mut act_count = 0;
-    let mut unknown_count = 0;
-    
-    for entry in &action_list.0 {
-        match entry {
-            ReaperEntry::Key(_) => key_count += 1,
-            ReaperEntry::Script(_) => scr_count += 1,
-            ReaperEntry::Action(_) => act_count += 1,
-        }
-    }
+    let counts = action_list.counts();
+    let key_count = counts.keys;
+    let scr_count = counts.scripts;
+    let act_count = counts.actions;
     
     println!("   📊 Entry type breakdown:");
     println!("      🔧 KEY entries: {}", key_count);
     println!("      📜 SCR entries: {}", scr_count);
     println!("      🎬 ACT entries: {}", act_count);
-    println!("      ❓ Unknown entries: {}", unknown_count);
     
     // Step 9: Analyze special inputs specifically
-    let special_input_count = action_list.0.iter()
+    let special_input_count = action_list.entries.iter()
         .filter(|entry| {
             if let ReaperEntry::Key(key_entry) = entry {
                 matches!(key_entry.key_input, KeyInputType::Special(_))
@@ -106,7 +98,7 @@ fn test_large_integration_with_scr_and_act_entries() {
     // Step 10: Analyze section distribution
     let mut section_counts = std::collections::HashMap::new();
     
-    for entry in &action_list.0 {
+    for entry in &action_list.entries {
         if let ReaperEntry::Key(key_entry) = entry {
             *section_counts.entry(key_entry.section).or_insert(0) += 1;
         }
@@ -118,7 +110,7 @@ fn test_large_integration_with_scr_and_act_entries() {
     }
     
     // Step 11: Find interesting mousewheel commands across all sections
-    let mousewheel_commands: Vec<String> = action_list.0.iter()
+    let mousewheel_commands: Vec<String> = action_list.entries.iter()
         .filter_map(|entry| {
             if let ReaperEntry::Key(key_entry) = entry {
                 if let KeyInputType::Special(special_input) = &key_entry.key_input {
@@ -158,14 +150,8 @@ fn test_large_integration_with_scr_and_act_entries() {
     // Step 12: Analyze SCR entries if present
     if scr_count > 0 {
         println!("   📜 SCR entry analysis:");
-        let scr_entries: Vec<_> = action_list.0.iter()
-            .filter_map(|entry| {
-                if let ReaperEntry::Script(scr_entry) = entry {
-                    Some(scr_entry)
-                } else {
-                    None
-                }
-            })
+        let scr_entries: Vec<_> = action_list.entries.iter()
+            .filter_map(ReaperEntry::as_script)
             .collect();
         
         // Show first few SCR entries
@@ -180,14 +166,8 @@ fn test_large_integration_with_scr_and_act_entries() {
     // Step 13: Analyze ACT entries if present
     if act_count > 0 {
         println!("   🎬 ACT entry analysis:");
-        let act_entries: Vec<_> = action_list.0.iter()
-            .filter_map(|entry| {
-                if let ReaperEntry::Action(act_entry) = entry {
-                    Some(act_entry)
-                } else {
-                    None
-                }
-            })
+        let act_entries: Vec<_> = action_list.entries.iter()
+            .filter_map(ReaperEntry::as_action)
             .collect();
         
         // Show first few ACT entries
@@ -216,8 +196,8 @@ fn test_large_integration_with_scr_and_act_entries() {
         .expect("Failed to deserialize from JSON");
     
     assert_eq!(
-        action_list.0.len(),
-        from_json.0.len(),
+        action_list.entries.len(),
+        from_json.entries.len(),
         "JSON round-trip entry count mismatch"
     );
     
@@ -251,7 +231,7 @@ fn test_large_integration_with_scr_and_act_entries() {
     println!("   📁 Generated large files available at:");
     println!("      Keymap: {:?}", generated_keymap_path);
     println!("      JSON:   {:?}", json_path);
-    println!("   📈 Parse success rate: {:.1}%", (matches as f64 / action_list.0.len() as f64) * 100.0);
+    println!("   📈 Parse success rate: {:.1}%", (matches as f64 / action_list.entries.len() as f64) * 100.0);
 }
 
 #[test]
@@ -259,13 +239,30 @@ fn test_large_file_performance() {
     // Performance test for large files
     println!("⚡ Testing large file parsing performance");
     
-    let original_path = "resources/large-integration-test.reaperkeymap";
-    
-    // Check if file exists first
-    if !std::path::Path::new(original_path).exists() {
-        println!("⚠️  Large test file not found, skipping performance test");
-        return;
-    }
+    let fixture = std::path::PathBuf::from("resources/large-integration-test.reaperkeymap");
+    
+    // Fall back to a generated keymap of similar size when the file isn't there
+    let generated;
+    let original_path = if fixture.exists() {
+        fixture
+    } else {
+        println!("⚠️  Large test file not found, using a generated keymap");
+        generated = tempfile::NamedTempFile::new().expect("Failed to create temp file");
+        KeymapGenerator::new()
+            .sections(&[
+                ReaperActionSection::Main,
+                ReaperActionSection::MidiEditor,
+                ReaperActionSection::MediaExplorer,
+            ])
+            .keys_per_section(600)
+            .include_scripts(200)
+            .include_custom_actions(100)
+            .include_special_inputs(true)
+            .build()
+            .save_to_file(generated.path())
+            .expect("Failed to write generated keymap");
+        generated.path().to_path_buf()
+    };
     
     let start_time = std::time::Instant::now();
     
@@ -280,10 +277,10 @@ fn test_large_file_performance() {
     let serialize_duration = start_serialize.elapsed();
     
     println!("⚡ Performance results:");
-    println!("   📊 Entries processed: {}", action_list.0.len());
+    println!("   📊 Entries processed: {}", action_list.entries.len());
     println!("   ⏱️  Parse time: {:.2}ms", parse_duration.as_millis());
     println!("   📝 Serialize time: {:.2}ms", serialize_duration.as_millis());
-    println!("   🚀 Parse rate: {:.0} entries/second", action_list.0.len() as f64 / parse_duration.as_secs_f64());
+    println!("   🚀 Parse rate: {:.0} entries/second", action_list.entries.len() as f64 / parse_duration.as_secs_f64());
     
     // Performance assertions
     assert!(parse_duration.as_millis() < 1000, "Parsing should complete within 1 second");
@@ -339,7 +336,7 @@ fn test_structured_comment_parsing_and_generation() {
         .expect("Failed to load real keymap file");
     
     // Count entries with comments
-    let entries_with_comments = action_list.0.iter()
+    let entries_with_comments = action_list.entries.iter()
         .filter_map(|entry| {
             if let ReaperEntry::Key(key_entry) = entry {
                 key_entry.comment.as_ref()
@@ -376,7 +373,7 @@ fn test_structured_comment_parsing_and_generation() {
     let reparsed_list = ReaperActionList::load_from_file(&generated_path)
         .expect("Failed to re-parse generated file with comments");
     
-    let reparsed_entries_with_comments = reparsed_list.0.iter()
+    let reparsed_entries_with_comments = reparsed_list.entries.iter()
         .filter_map(|entry| {
             if let ReaperEntry::Key(key_entry) = entry {
                 key_entry.comment.as_ref()
@@ -389,9 +386,7 @@ fn test_structured_comment_parsing_and_generation() {
     println!("   🔄 Re-parsed entries with comments: {}", reparsed_entries_with_comments);
     
     // Since we now generate comments for all entries, reparsed should have all entries with comments
-    let total_key_entries = reparsed_list.0.iter()
-        .filter(|entry| matches!(entry, ReaperEntry::Key(_)))
-        .count();
+    let total_key_entries = reparsed_list.counts().keys;
     
     println!("   📈 Total KEY entries: {}", total_key_entries);
     println!("   📈 Entries with comments after round-trip: {}", reparsed_entries_with_comments);
@@ -412,6 +407,7 @@ fn test_structured_comment_parsing_and_generation() {
         command_id: "40044".to_string(),
         section: ReaperActionSection::Main,
         comment: None,
+        trailing: None,
     };
     
     let regular_comment = regular_key_entry.generate_comment();
@@ -424,6 +420,7 @@ fn test_structured_comment_parsing_and_generation() {
         command_id: "989".to_string(),
         section: ReaperActionSection::Main,
         comment: None,
+        trailing: None,
     };
     
     let special_comment = special_key_entry.generate_comment();
@@ -436,6 +433,7 @@ fn test_structured_comment_parsing_and_generation() {
         command_id: "0".to_string(),
         section: ReaperActionSection::Main,
         comment: None,
+        trailing: None,
     };
     
     let disabled_comment = disabled_key_entry.generate_comment();
@@ -531,7 +529,7 @@ fn test_midi_relative_action_parsing() {
         .expect("Failed to load real keymap file");
     
     // Find all MIDI relative entries
-    let midi_relative_entries: Vec<_> = action_list.0.iter()
+    let midi_relative_entries: Vec<_> = action_list.entries.iter()
         .filter_map(|entry| {
             if let ReaperEntry::Key(key_entry) = entry {
                 if let Some(ref comment) = key_entry.comment {
@@ -605,7 +603,7 @@ fn test_midi_relative_action_parsing() {
     let from_json: ReaperActionList = serde_json::from_str(&json_content)
         .expect("Failed to deserialize from JSON");
     
-    let reparsed_midi_entries = from_json.0.iter()
+    let reparsed_midi_entries = from_json.entries.iter()
         .filter_map(|entry| {
             if let ReaperEntry::Key(key_entry) = entry {
                 if let Some(ref comment) = key_entry.comment {
@@ -686,6 +684,36 @@ fn test_special_input_coverage() {
     println!("✅ Special input coverage test passed");
 }
 
+#[test]
+fn test_special_bindings_by_family() {
+    let action_list = ReaperActionList::from_lines([
+        "KEY 255 248 40432 32060",  // Mousewheel
+        "KEY 255 250 40431 32060",  // Alt+Mousewheel
+        "KEY 255 218 40660 32060",  // Alt+HorizWheel
+        "KEY 255 220 40138 32060",  // Shift+HorizWheel
+        "KEY 255 252 40139 32060",  // Shift+Mousewheel
+        "KEY 255 216 989 0",        // HorizWheel
+        "KEY 255 200 40454 32060",  // MultiZoom
+        "KEY 1 65 40001 0",         // Ctrl+A, not a special input
+    ])
+    .expect("Failed to parse special input lines");
+
+    assert_eq!(action_list.special_bindings(None).len(), 7);
+    assert_eq!(action_list.special_bindings(Some(SpecialInputBase::Mousewheel)).len(), 3);
+    assert_eq!(action_list.special_bindings(Some(SpecialInputBase::MultiZoom)).len(), 1);
+    assert!(action_list.special_bindings(Some(SpecialInputBase::MultiRotate)).is_empty());
+
+    // Sorted by section, so the Main binding comes first
+    let horiz = action_list.special_bindings(Some(SpecialInputBase::HorizWheel));
+    let ids: Vec<&str> = horiz.iter().map(|k| k.command_id.as_str()).collect();
+    assert_eq!(ids[0], "989");
+    assert_eq!(ids.len(), 3);
+
+    let by_section = action_list.special_bindings_by_section(Some(SpecialInputBase::HorizWheel));
+    assert_eq!(by_section[&ReaperActionSection::Main].len(), 1);
+    assert_eq!(by_section[&ReaperActionSection::MidiEditor].len(), 2);
+}
+
 #[test]
 fn test_json_schema_structure() {
     // Test the JSON schema structure makes sense